@@ -0,0 +1,1124 @@
+//! The command-line front end: the interactive menu game and the
+//! one-shot subcommands (`xor`, `enigma`, `train`, ...).
+
+use std::cell::Cell;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use vigenere_bruteforce::*;
+
+const CIPHERTEXT: &str = "bxrworn, dodcx iy lbks !";
+
+// The config file read when it exists and no other is named.
+const CONFIG_PATH: &str = "vigenere_bruteforce.toml";
+
+// Where sweeps keep their checkpoint, in the working directory.
+const CHECKPOINT_PATH: &str = "vigenere_bruteforce.checkpoint";
+
+// Where the batch command writes its JSON report unless told otherwise.
+const BATCH_REPORT_PATH: &str = "vigenere_bruteforce.report.json";
+
+// Set by the first Ctrl-C. Searches are given it as their stop flag, so they
+// wind down and the results found so far can still be shown.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
+}
+
+fn clear_interrupt() {
+    INTERRUPTED.store(false, Ordering::Relaxed);
+}
+
+// A second Ctrl-C while a search is winding down quits immediately.
+#[cfg(unix)]
+fn install_interrupt_handler() {
+    const SIGINT: i32 = 2;
+    unsafe extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn _exit(status: i32) -> !;
+    }
+    extern "C" fn on_interrupt(_: i32) {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            unsafe { _exit(130) }
+        }
+    }
+    unsafe {
+        signal(SIGINT, on_interrupt);
+    }
+}
+
+#[cfg(not(unix))]
+fn install_interrupt_handler() {}
+
+// Prints status updates from the solvers: lines as they come, and sweep
+// progress on a single line redrawn in place when stdout is a terminal.
+struct StatusPrinter {
+    terminal: bool,
+    progress_shown: Cell<bool>,
+}
+
+impl StatusPrinter {
+    fn new() -> Self {
+        StatusPrinter { terminal: io::IsTerminal::is_terminal(&io::stdout()), progress_shown: Cell::new(false) }
+    }
+
+    fn print(&self, status: Status) {
+        match status {
+            Status::Message(line) => {
+                self.clear_progress();
+                println!("{}", line);
+            }
+            Status::Progress { tried, total, rate, eta, best } if self.terminal => {
+                let best = match best {
+                    Some((score, preview)) => {
+                        let preview: String = preview.chars().take(30).map(|c| if c.is_control() { ' ' } else { c }).collect();
+                        format!("best {}: {}", score, preview)
+                    }
+                    None => "no candidates yet".to_string(),
+                };
+                print!(
+                    "\r\x1b[K  ⏳ {}/{} keys ({:.1}%) | {:.0} keys/s | ETA {} | {}",
+                    tried,
+                    total,
+                    tried as f64 * 100.0 / total.max(1) as f64,
+                    rate,
+                    format_duration(eta),
+                    best
+                );
+                io::stdout().flush().unwrap();
+                self.progress_shown.set(true);
+            }
+            Status::Progress { .. } => {}
+            Status::ProgressDone => self.clear_progress(),
+        }
+    }
+
+    // Wipes the progress line so a normal message can be printed.
+    fn clear_progress(&self) {
+        if self.progress_shown.replace(false) {
+            print!("\r\x1b[K");
+            io::stdout().flush().unwrap();
+        }
+    }
+}
+
+// What the global flags set up, shared by every command.
+struct Context {
+    scorer: Scorer,
+    config: SearchConfig,
+    report: Box<dyn Fn(Status)>,
+    // `--fold-accents`: fold accented letters (é -> e) in the text to crack.
+    fold: bool,
+}
+
+impl Context {
+    // The ciphertext (or other input text) a command works on, folded if
+    // asked. Paths and flag values are never folded.
+    fn input(&self, text: &str) -> String {
+        if self.fold { fold_accents(text) } else { text.to_string() }
+    }
+
+    // A session that stops on Ctrl-C and prints its status updates.
+    fn session(&self) -> Session<'_> {
+        Session::new(&self.scorer, &self.config).with_stop_flag(&INTERRUPTED).with_reporter(&*self.report)
+    }
+}
+
+fn get_user_choice() -> usize {
+    loop {
+        println!("\n════════════════════════════════════════════════");
+        println!("Choose a cipher to test (or 0 to test all):");
+        println!(" 1. Caesar Cipher");
+        println!(" 2. ROT13");
+        println!(" 3. Atbash");
+        println!(" 4. Vigenère Cipher");
+        println!(" 5. Rail Fence Cipher");
+        println!(" 6. Affine Cipher");
+        println!(" 7. Beaufort Cipher");
+        println!(" 8. Columnar Transposition");
+        println!(" 9. Playfair Cipher");
+        println!("10. Polybius Square");
+        println!("11. Bacon Cipher");
+        println!("12. Reverse Cipher");
+        println!("13. Atbash + Vigenère Hybrid");
+        println!("14. XOR (single-byte + repeating-key)");
+        println!("15. Cipher Pipeline Search (up to 3 stages)");
+        println!("16. Running Key (statistical split)");
+        println!("17. Enigma I (rotor search + plugboard)");
+        println!("18. Homophonic Substitution (symbol tokens)");
+        println!("19. Straddling Checkerboard (digit ciphertext)");
+        println!("20. Chaocipher / Solitaire (passphrase wordlist)");
+        println!("21. Keyword Substitution (wordlist × 26 shifts)");
+        println!(" 0. Test ALL ciphers (Brute Force All)");
+        print!("\nYour choice (0-{}): ", CIPHER_COUNT);
+        io::stdout().flush().unwrap();
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+        
+        match input.trim().parse::<usize>() {
+            Ok(choice) if choice <= CIPHER_COUNT => return choice,
+            _ => println!("Invalid choice. Please enter a number between 0 and {}.", CIPHER_COUNT),
+        }
+    }
+}
+
+fn display_results(ctx: &Context, top_n: &TopN) {
+    let results = top_n.sorted_results();
+    
+    if results.is_empty() {
+        println!("\n❌ No results found!");
+        return;
+    }
+    
+    if interrupted() {
+        println!("\n⚠️  Search interrupted (Ctrl-C). Showing the best results found so far.");
+    }
+
+    let confidences = assess_results(&ctx.scorer, &results);
+    let threshold = ctx.config.success_confidence();
+    if confidences[0].percent >= threshold {
+        println!("\n✅ SUCCESS! Found the correct decryption ({}% confidence).", confidences[0].percent);
+    } else {
+        println!("\n❌ No confident match (best is {}%, success needs {}%). Here are the best candidates:", confidences[0].percent, threshold);
+    }
+    
+    println!("\n🏆 TOP RESULTS:");
+    println!("{}════════════════════════════════════════════════", "═".repeat(25));
+    
+    for (rank, (result, confidence)) in results.iter().zip(&confidences).enumerate() {
+        println!("  #{:<2} | Score: {:<4} | Conf: {:>3}% | Lang: {} | Type: {:<15} | Params: {}", 
+                 rank + 1, result.score, confidence.percent, confidence.language.code(), result.cipher_type, result.params);
+        println!("       └─ {}", &result.plaintext_preview);
+        if !result.equivalents.is_empty() {
+            println!("          ≡ same as: {}", result.equivalents.join("; "));
+        }
+        println!();
+    }
+    
+    // Show the best candidate in full
+    let (best, confidence) = (&results[0], &confidences[0]);
+    println!("\n📝 BEST CANDIDATE:");
+    println!("{}════════════════════════════════════════════════", "═".repeat(25));
+    println!("Cipher: {} | Params: {}", best.cipher_type, best.params);
+    println!("Score: {} | Language: {}", best.score, confidence.language.name());
+    println!(
+        "Confidence: {}% (fitness z = {:.1}, dictionary {:.0}%, margin {:.0}%)",
+        confidence.percent,
+        confidence.z_score,
+        confidence.coverage * 100.0,
+        confidence.margin * 100.0
+    );
+    println!("\nDecrypted text:");
+    println!("{}\n", best.plaintext_full);
+}
+
+// `xor [--hex|--base64|--raw] <file>`: cracks a binary file with the
+// single-byte and repeating-key XOR attacks.
+fn run_xor_command(ctx: &Context, args: &[String]) {
+    let mut format = "auto";
+    let mut path = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "--hex" => format = "hex",
+            "--base64" => format = "base64",
+            "--raw" => format = "raw",
+            other => path = Some(other),
+        }
+    }
+
+    let Some(path) = path else {
+        eprintln!("Usage: vigenere_bruteforce xor [--hex|--base64|--raw] <file>");
+        return;
+    };
+
+    let raw = match std::fs::read(path) {
+        Ok(raw) => raw,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path, e);
+            return;
+        }
+    };
+
+    let text = String::from_utf8_lossy(&raw);
+    let bytes = match format {
+        "hex" => hex_to_bytes(&text),
+        "base64" => base64_to_bytes(&text),
+        "raw" => Some(raw.clone()),
+        _ => Some(ciphertext_bytes(&text)),
+    };
+
+    let Some(bytes) = bytes else {
+        eprintln!("{} is not valid {}", path, format);
+        return;
+    };
+
+    println!("\n🔍 Cracking {} bytes of XOR ciphertext from {}...", bytes.len(), path);
+    let mut top_n = TopN::new(5);
+    let session = ctx.session();
+    crack_single_byte_xor(&session, &bytes, &mut top_n);
+    crack_repeating_xor(&session, &bytes, &mut top_n);
+    display_results(ctx, &top_n);
+}
+
+// `pipeline "<spec>" [ciphertext]`: runs a user-described stage chain such
+// as `reverse | caesar(?) | railfence(?)` against the ciphertext.
+fn run_pipeline_command(ctx: &Context, args: &[String]) {
+    let Some(spec) = args.first() else {
+        eprintln!("Usage: vigenere_bruteforce pipeline \"<stage> | <stage>(?) | ...\" [ciphertext]");
+        return;
+    };
+    let ciphertext = ctx.input(args.get(1).map(String::as_str).unwrap_or(CIPHERTEXT));
+
+    let templates = match parse_pipeline(spec) {
+        Ok(templates) => templates,
+        Err(e) => {
+            eprintln!("Invalid pipeline: {}", e);
+            return;
+        }
+    };
+
+    println!("\n🔍 Searching pipeline \"{}\" on \"{}\"...", spec, ciphertext);
+    let mut top_n = TopN::new(5);
+    crack_pipeline(&ctx.session(), &ciphertext, &templates, &mut top_n);
+    display_results(ctx, &top_n);
+}
+
+// `runkey <corpus-file> [ciphertext]`: tries every offset of a local text as
+// the running key.
+fn run_running_key_command(ctx: &Context, args: &[String]) {
+    let Some(path) = args.first() else {
+        eprintln!("Usage: vigenere_bruteforce runkey <corpus-file> [ciphertext]");
+        return;
+    };
+    let ciphertext = ctx.input(args.get(1).map(String::as_str).unwrap_or(CIPHERTEXT));
+
+    let corpus = match std::fs::read_to_string(path) {
+        Ok(corpus) => corpus,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", path, e);
+            return;
+        }
+    };
+
+    println!("\n🔍 Sliding running key over {}...", path);
+    let session = ctx.session();
+    let mut top_n = TopN::new(5);
+    crack_running_key(&session, &ciphertext, &corpus, &mut top_n);
+    crack_running_key_statistical(&session, &ciphertext, &mut top_n);
+    display_results(ctx, &top_n);
+}
+
+// `book <text-file> <references...>`: decodes page:line:word, line:word or
+// word-index references against a local text.
+fn run_book_command(ctx: &Context, args: &[String]) {
+    if args.len() < 2 {
+        eprintln!("Usage: vigenere_bruteforce book <text-file> <page:line:word | line:word | index>...");
+        return;
+    }
+
+    let book = match std::fs::read_to_string(&args[0]) {
+        Ok(book) => book,
+        Err(e) => {
+            eprintln!("Could not read {}: {}", args[0], e);
+            return;
+        }
+    };
+
+    println!("\n🔍 Decoding book cipher against {}...", args[0]);
+    let mut top_n = TopN::new(5);
+    crack_book_cipher(&ctx.session(), &args[1..].join(" "), &book, &mut top_n);
+    display_results(ctx, &top_n);
+}
+
+// `enigma [--reflector B] [--rotors I,II,III] [--rings AAA] [--positions AAA]
+// [--plugs "AB CD"] <text>` enciphers/deciphers; `enigma --crack [--model
+// I|M3|M4] [ciphertext]` runs the ciphertext-only attack.
+fn run_enigma_command(ctx: &Context, args: &[String]) {
+    let mut reflector = "B".to_string();
+    let mut rotors = "I,II,III".to_string();
+    let mut rings = None;
+    let mut positions = None;
+    let mut plugs = String::new();
+    let mut model = EnigmaModel::I;
+    let mut crack = false;
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().cloned().unwrap_or_default();
+        match arg.as_str() {
+            "--reflector" => reflector = value(),
+            "--rotors" => rotors = value(),
+            "--rings" => rings = Some(value()),
+            "--positions" => positions = Some(value()),
+            "--plugs" => plugs = value(),
+            "--crack" => crack = true,
+            "--model" => match EnigmaModel::parse(&value()) {
+                Some(m) => model = m,
+                None => {
+                    eprintln!("Unknown Enigma model (expected I, M3 or M4)");
+                    return;
+                }
+            },
+            other => words.push(other),
+        }
+    }
+    let text = if words.is_empty() { None } else { Some(ctx.input(&words.join(" "))) };
+
+    if crack {
+        let ciphertext = text.unwrap_or_else(|| ctx.input(CIPHERTEXT));
+        println!("\n🔍 Attacking Enigma ciphertext (this searches every rotor order)...");
+        let mut top_n = TopN::new(5);
+        crack_enigma(&ctx.session(), &ciphertext, model, &mut top_n);
+        display_results(ctx, &top_n);
+        return;
+    }
+
+    let Some(text) = text else {
+        eprintln!("Usage: vigenere_bruteforce enigma [--reflector B] [--rotors I,II,III] [--rings AAA] [--positions AAA] [--plugs \"AB CD\"] <text>");
+        eprintln!("       vigenere_bruteforce enigma --crack [--model I|M3|M4] [ciphertext]");
+        return;
+    };
+
+    let width = if rotors.split([',', '-']).count() == 4 { 4 } else { 3 };
+    let rings = rings.unwrap_or_else(|| "A".repeat(width));
+    let positions = positions.unwrap_or_else(|| "A".repeat(width));
+
+    match parse_enigma_settings(&reflector, &rotors, &rings, &positions, &plugs) {
+        Ok(settings) => println!("{}", decrypt_enigma(&text, &settings)),
+        Err(e) => eprintln!("Invalid Enigma settings: {}", e),
+    }
+}
+
+// `homophonic <file | ciphertext>`: solves a tokenized homophonic cipher
+// (whitespace-separated numbers or single glyphs).
+fn run_homophonic_command(ctx: &Context, args: &[String]) {
+    let Some(arg) = args.first() else {
+        eprintln!("Usage: vigenere_bruteforce homophonic <file | ciphertext>");
+        return;
+    };
+    let ciphertext = if std::path::Path::new(arg).is_file() {
+        match std::fs::read_to_string(arg) {
+            Ok(text) => ctx.input(&text),
+            Err(e) => {
+                eprintln!("Could not read {}: {}", arg, e);
+                return;
+            }
+        }
+    } else {
+        ctx.input(&args.join(" "))
+    };
+
+    let symbols: std::collections::HashSet<String> = tokenize_symbols(&ciphertext).into_iter().collect();
+    println!("\n🔍 Annealing homophonic key for {} distinct symbols...", symbols.len());
+    let mut top_n = TopN::new(5);
+    crack_homophonic(&ctx.session(), &ciphertext, ctx.config.seed(), &mut top_n);
+    display_results(ctx, &top_n);
+}
+
+// `checkerboard encrypt|decrypt --key KEYWORD --blanks 2,6 [--transpose KEY]
+// <text>` or `checkerboard crack [--vic] <digits>`.
+fn run_checkerboard_command(ctx: &Context, args: &[String]) {
+    let usage = || {
+        eprintln!("Usage: vigenere_bruteforce checkerboard encrypt|decrypt --key KEYWORD --blanks 2,6 [--transpose KEY] <text>");
+        eprintln!("       vigenere_bruteforce checkerboard crack [--vic] <digits>");
+    };
+    let Some(mode) = args.first() else {
+        return usage();
+    };
+
+    let mut keyword = String::new();
+    let mut blanks = [2u8, 6u8];
+    let mut transpose = None;
+    let mut vic = false;
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--key" => keyword = iter.next().cloned().unwrap_or_default(),
+            "--transpose" => transpose = iter.next().cloned(),
+            "--vic" => vic = true,
+            "--blanks" => {
+                let value = iter.next().cloned().unwrap_or_default();
+                let digits: Vec<u8> = digit_values(&value);
+                match digits.as_slice() {
+                    [a, b] if a != b => blanks = [*a.min(b), *a.max(b)],
+                    _ => {
+                        eprintln!("--blanks needs two different digits, e.g. 2,6");
+                        return;
+                    }
+                }
+            }
+            other => words.push(other),
+        }
+    }
+    let text = ctx.input(&words.join(" "));
+    let board = match Checkerboard::from_keyword(&keyword, blanks) {
+        Ok(board) => board,
+        Err(e) => {
+            eprintln!("Invalid checkerboard: {}", e);
+            return;
+        }
+    };
+
+    match mode.as_str() {
+        "encrypt" => {
+            let digits = encrypt_checkerboard(&text, &board);
+            match transpose.map(|key| encrypt_columnar_transposition(&digits, &key)) {
+                Some(Ok(transposed)) => println!("{}", transposed),
+                Some(Err(e)) => eprintln!("Invalid transposition key: {}", e),
+                None => println!("{}", digits),
+            }
+        }
+        "decrypt" => {
+            let digits: String = text.chars().filter(|c| c.is_ascii_digit()).collect();
+            let digits = match transpose.map(|key| decrypt_columnar_transposition(&digits, &key)) {
+                Some(Ok(untransposed)) => untransposed,
+                Some(Err(e)) => {
+                    eprintln!("Invalid transposition key: {}", e);
+                    return;
+                }
+                None => digits,
+            };
+            println!("{}", decrypt_checkerboard(&digits, &board));
+        }
+        "crack" => {
+            println!("\n🔍 Solving straddling checkerboard{}...", if vic { " (with transposition)" } else { "" });
+            let mut top_n = TopN::new(5);
+            crack_checkerboard(&ctx.session(), &text, vic, &mut top_n);
+            display_results(ctx, &top_n);
+        }
+        _ => usage(),
+    }
+}
+
+// `chaocipher encrypt|decrypt (--pass PHRASE | --left ALPHABET --right
+// ALPHABET) <text>` or `chaocipher crack --crib PLAINTEXT <ciphertext>`.
+fn run_chaocipher_command(ctx: &Context, args: &[String]) {
+    let usage = || {
+        eprintln!("Usage: vigenere_bruteforce chaocipher encrypt|decrypt (--pass PHRASE | --left ALPHABET --right ALPHABET) <text>");
+        eprintln!("       vigenere_bruteforce chaocipher crack --crib PLAINTEXT <ciphertext>");
+    };
+    let Some(mode) = args.first() else {
+        return usage();
+    };
+
+    let mut passphrase = String::new();
+    let mut left = None;
+    let mut right = None;
+    let mut crib = String::new();
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--pass" => passphrase = iter.next().cloned().unwrap_or_default(),
+            "--left" => left = iter.next().cloned(),
+            "--right" => right = iter.next().cloned(),
+            "--crib" => crib = iter.next().cloned().unwrap_or_default(),
+            other => words.push(other),
+        }
+    }
+    let text = ctx.input(&words.join(" "));
+
+    let cipher = match (left, right) {
+        (Some(left), Some(right)) => match Chaocipher::new(&left, &right) {
+            Ok(cipher) => cipher,
+            Err(e) => {
+                eprintln!("Invalid Chaocipher wheels: {}", e);
+                return;
+            }
+        },
+        _ => Chaocipher::from_passphrase(&passphrase),
+    };
+
+    match mode.as_str() {
+        "encrypt" => println!("{}", cipher.encrypt(&text)),
+        "decrypt" => println!("{}", cipher.decrypt(&text)),
+        "crack" => {
+            let plain = letter_values(&ctx.input(&crib));
+            let cipher_letters = letter_values(&text);
+            if plain.is_empty() || cipher_letters.is_empty() {
+                return usage();
+            }
+            println!("\n🔍 Reconstructing Chaocipher wheels from {} known letters...", plain.len().min(cipher_letters.len()));
+            match crack_chaocipher_known_plaintext(&ctx.session(), &plain, &cipher_letters) {
+                Some(cipher) => {
+                    println!("✅ {}", cipher.describe());
+                    println!("{}", cipher.decrypt(&text));
+                }
+                None => println!("❌ No wheel pair fits the crib (or the search was stopped or hit its limit)."),
+            }
+        }
+        _ => usage(),
+    }
+}
+
+// `solitaire encrypt|decrypt [--pass PHRASE] <text>`: without a passphrase
+// the deck starts in bridge order.
+fn run_solitaire_command(ctx: &Context, args: &[String]) {
+    let usage = || eprintln!("Usage: vigenere_bruteforce solitaire encrypt|decrypt [--pass PHRASE] [--show-deck] <text>");
+    let Some(mode) = args.first() else {
+        return usage();
+    };
+
+    let mut passphrase = String::new();
+    let mut show_deck = false;
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--pass" => passphrase = iter.next().cloned().unwrap_or_default(),
+            "--show-deck" => show_deck = true,
+            other => words.push(other),
+        }
+    }
+    let text = ctx.input(&words.join(" "));
+    let cipher = Solitaire::from_passphrase(&passphrase);
+    if show_deck {
+        println!("Deck: {}", cipher.describe());
+    }
+
+    match mode.as_str() {
+        "encrypt" => println!("{}", cipher.encrypt(&text)),
+        "decrypt" => println!("{}", cipher.decrypt(&text)),
+        _ => usage(),
+    }
+}
+
+// `shift [--alphabet NAME] [caesar N | vigenere KEY | beaufort KEY |
+// affine A,B] <text>`: decrypts with the given key over the chosen alphabet,
+// or brute forces the whole shift family when no cipher is named.
+fn run_shift_command(ctx: &Context, args: &[String]) {
+    let usage = || {
+        eprintln!("Usage: vigenere_bruteforce shift [--alphabet az|az09|base64|printable|<symbols>] [caesar N | vigenere KEY | beaufort KEY | affine A,B] <text>");
+    };
+
+    let mut alphabet = Alphabet::letters();
+    let mut cipher: Option<(String, String)> = None;
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--alphabet" => match Alphabet::parse(&iter.next().cloned().unwrap_or_default()) {
+                Ok(a) => alphabet = a,
+                Err(e) => {
+                    eprintln!("Invalid alphabet: {}", e);
+                    return;
+                }
+            },
+            "caesar" | "vigenere" | "beaufort" | "affine" if cipher.is_none() && words.is_empty() => {
+                cipher = Some((arg.clone(), iter.next().cloned().unwrap_or_default()));
+            }
+            other => words.push(other),
+        }
+    }
+    if words.is_empty() {
+        return usage();
+    }
+    let text = ctx.input(&words.join(" "));
+
+    let Some((name, key)) = cipher else {
+        println!("\n🔍 Brute forcing shift ciphers over the {} alphabet ({} symbols)...", alphabet.name(), alphabet.len());
+        let mut top_n = TopN::new(5);
+        crack_shift_ciphers_in(&ctx.session(), &text, &alphabet, &mut top_n);
+        display_results(ctx, &top_n);
+        return;
+    };
+
+    let number = |n: &str| n.trim().parse::<usize>().map_err(|_| CipherError::InvalidKey(format!("'{}' is not a number", n.trim())));
+    let plain = match name.as_str() {
+        "caesar" => number(&key).map(|shift| decrypt_caesar_in(&text, shift, &alphabet)),
+        "vigenere" => alphabet.key_values(&key).and_then(|k| decrypt_vigenere_in(&text, &k, &alphabet)),
+        "beaufort" => alphabet.key_values(&key).and_then(|k| decrypt_beaufort_in(&text, &k, &alphabet)),
+        _ => match key.split_once(',') {
+            Some((a, b)) => number(a).and_then(|a| decrypt_affine_in(&text, a, number(b)?, &alphabet)),
+            None => Err(CipherError::InvalidKey("affine keys are written A,B".to_string())),
+        },
+    };
+    match plain {
+        Ok(plain) => println!("{}", plain),
+        Err(e) => eprintln!("Cannot decrypt {} over the {} alphabet: {}", name, alphabet.name(), e),
+    }
+}
+
+// `keyword [--wordlist FILE] <ciphertext>` runs the dictionary attack;
+// `keyword --key WORD [--shift N] [--from-last] <ciphertext>` decrypts.
+fn run_keyword_command(ctx: &Context, args: &[String]) {
+    let usage = || {
+        eprintln!("Usage: vigenere_bruteforce keyword [--wordlist FILE] <ciphertext>");
+        eprintln!("       vigenere_bruteforce keyword --key WORD [--shift N] [--from-last] <ciphertext>");
+    };
+
+    let mut wordlist = None;
+    let mut keyword = None;
+    let mut shift = 0u8;
+    let mut from_last = false;
+    let mut words: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--wordlist" => wordlist = iter.next().cloned(),
+            "--key" => keyword = iter.next().cloned(),
+            "--shift" => match iter.next().and_then(|v| v.parse::<u8>().ok()) {
+                Some(n) => shift = n % 26,
+                None => return usage(),
+            },
+            "--from-last" => from_last = true,
+            other => words.push(other),
+        }
+    }
+    if words.is_empty() {
+        return usage();
+    }
+    let text = ctx.input(&words.join(" "));
+
+    if let Some(keyword) = keyword {
+        match decrypt_keyword(&text, &keyword_alphabet(&keyword, from_last), shift) {
+            Ok(plain) => println!("{}", plain),
+            Err(e) => eprintln!("Could not decrypt: {}", e),
+        }
+        return;
+    }
+
+    let contents = match wordlist {
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", path, e);
+                return;
+            }
+        },
+        None => String::new(),
+    };
+    let candidates: Vec<&str> = if contents.is_empty() {
+        COMMON_WORDS.to_vec()
+    } else {
+        contents.split_whitespace().collect()
+    };
+
+    println!("\n🔍 Trying {} keywords × 26 shifts...", candidates.len());
+    let mut top_n = TopN::new(5);
+    crack_keyword_substitution(&ctx.session(), &text, &candidates, &mut top_n);
+    if let Some(best) = top_n.best_result() {
+        println!("🔑 Best keyword candidate: {}", best.params);
+    }
+    display_results(ctx, &top_n);
+}
+
+// `train --output FILE [--words N] <text files...>`: builds n-gram tables
+// and a word list from local text for use with `--model FILE`.
+fn run_train_command(args: &[String]) {
+    let usage = || eprintln!("Usage: vigenere_bruteforce train --output FILE [--words N] <text files...>");
+
+    let mut output = None;
+    let mut word_limit = DEFAULT_TRAINED_WORDS;
+    let mut inputs: Vec<&str> = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = iter.next().cloned(),
+            "--words" => match iter.next().and_then(|v| v.parse().ok()) {
+                Some(n) => word_limit = n,
+                None => return usage(),
+            },
+            other => inputs.push(other),
+        }
+    }
+    let Some(output) = output else {
+        return usage();
+    };
+    if inputs.is_empty() {
+        return usage();
+    }
+
+    let mut counts = TrainingCounts::new();
+    for path in inputs {
+        match std::fs::read(path) {
+            Ok(bytes) => counts.add_text(&String::from_utf8_lossy(&bytes)),
+            Err(e) => {
+                eprintln!("Could not read {}: {}", path, e);
+                return;
+            }
+        }
+    }
+
+    let letters: u64 = counts.ngrams[0].iter().sum();
+    if letters == 0 {
+        eprintln!("No letters found in the training text");
+        return;
+    }
+    let bytes = encode_model(&counts, word_limit);
+    match std::fs::write(&output, &bytes) {
+        Ok(()) => {
+            let quadgrams = counts.ngrams[3].iter().filter(|&&c| c > 0).count();
+            println!("✅ Trained on {} letters ({} distinct words, {} quadgrams seen)", letters, counts.words.len(), quadgrams);
+            println!("   Saved {} ({} KB). Use it with --model {}", output, bytes.len() / 1024, output);
+        }
+        Err(e) => eprintln!("Could not write {}: {}", output, e),
+    }
+}
+
+// One input per non-empty line of a file, or one per file in a directory
+// (the whole file, so ciphertexts there can span lines).
+fn read_batch_inputs(path: &str) -> std::result::Result<Vec<BatchInput>, String> {
+    let path = std::path::Path::new(path);
+    let name_of = |p: &std::path::Path| p.file_name().map_or_else(|| p.display().to_string(), |n| n.to_string_lossy().into_owned());
+
+    if !path.is_dir() {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let name = name_of(path);
+        return Ok(text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| BatchInput { name: format!("{}:{}", name, i + 1), ciphertext: line.trim().to_string() })
+            .collect());
+    }
+
+    let entries = std::fs::read_dir(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut files: Vec<std::path::PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_file()).collect();
+    files.sort();
+
+    let mut inputs = Vec::new();
+    for file in files {
+        match std::fs::read_to_string(&file) {
+            Ok(text) if !text.trim().is_empty() => {
+                inputs.push(BatchInput { name: name_of(&file), ciphertext: text.trim().to_string() })
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Skipping {}: {}", file.display(), e),
+        }
+    }
+    Ok(inputs)
+}
+
+// Cuts `text` to `width` characters (marking the cut with …) and pads it out.
+fn table_cell(text: &str, width: usize) -> String {
+    let text: String = text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+    if text.chars().count() > width {
+        format!("{}…", text.chars().take(width - 1).collect::<String>())
+    } else {
+        format!("{:<width$}", text)
+    }
+}
+
+// `batch [--jobs N] [--json FILE] <file | directory>`: cracks many short
+// ciphertexts at once, each with the same all-ciphers search as menu choice
+// 0, then prints a summary table and writes a JSON report.
+fn run_batch_command(ctx: &Context, args: &[String]) {
+    let usage = || eprintln!("Usage: vigenere_bruteforce batch [--jobs N] [--json FILE] <file | directory>");
+
+    let mut jobs = std::thread::available_parallelism().map_or(1, |n| n.get());
+    let mut report_path = BATCH_REPORT_PATH.to_string();
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--jobs" | "-j" => match iter.next().and_then(|v| v.parse().ok()).filter(|&n| n > 0) {
+                Some(n) => jobs = n,
+                None => return usage(),
+            },
+            "--json" => match iter.next() {
+                Some(p) => report_path = p.clone(),
+                None => return usage(),
+            },
+            other => path = Some(other),
+        }
+    }
+    let Some(path) = path else {
+        return usage();
+    };
+
+    let inputs: Vec<BatchInput> = match read_batch_inputs(path) {
+        Ok(inputs) => inputs.into_iter().map(|input| BatchInput { ciphertext: ctx.input(&input.ciphertext), ..input }).collect(),
+        Err(e) => {
+            eprintln!("Could not read batch input {}", e);
+            return;
+        }
+    };
+    if inputs.is_empty() {
+        eprintln!("No ciphertexts found in {}", path);
+        return;
+    }
+
+    let threshold = ctx.config.success_confidence();
+    println!("\n🚀 Cracking {} ciphertexts from {} ({} at a time)...", inputs.len(), path, jobs.min(inputs.len()));
+    let done = std::sync::atomic::AtomicUsize::new(0);
+    let outcomes = crack_batch(&ctx.session(), &inputs, jobs, |outcome| {
+        let done = done.fetch_add(1, std::sync::atomic::Ordering::Relaxed) + 1;
+        let icon = if outcome.flagged { "⚠️ " } else { "✅" };
+        let found = match (&outcome.best, &outcome.confidence) {
+            (Some(best), Some(confidence)) => format!("{} {} ({}%)", best.cipher_type, best.params, confidence.percent),
+            _ => "no result".to_string(),
+        };
+        println!("  {} [{}/{}] {}: {} in {}", icon, done, inputs.len(), outcome.name, found, format_duration(outcome.elapsed.as_secs_f64()));
+    });
+
+    let flagged = outcomes.iter().filter(|o| o.flagged).count();
+    println!("\n📋 BATCH SUMMARY: {} cracked, {} flagged below {}% confidence", outcomes.len() - flagged, flagged, threshold);
+    println!("{}════════════════════════════════════════════════", "═".repeat(25));
+    println!("  {} | {} | {} | Conf | {} | Flag", table_cell("Input", 16), table_cell("Cipher", 14), table_cell("Key", 20), table_cell("Plaintext", 40));
+    for outcome in &outcomes {
+        let best = outcome.best.as_ref();
+        println!(
+            "  {} | {} | {} | {:>3}% | {} | {}",
+            table_cell(&outcome.name, 16),
+            table_cell(best.map_or("-", |b| b.cipher_type.as_str()), 14),
+            table_cell(best.map_or("-", |b| b.params.as_str()), 20),
+            outcome.confidence.as_ref().map_or(0, |c| c.percent),
+            table_cell(best.map_or("", |b| b.plaintext_full.as_str()), 40),
+            if outcome.flagged { "⚠️  low" } else { "" }
+        );
+    }
+    if outcomes.len() < inputs.len() {
+        println!("\n⚠️  Interrupted (Ctrl-C): {} of {} inputs were not started.", inputs.len() - outcomes.len(), inputs.len());
+    }
+
+    match std::fs::write(&report_path, batch_report_json(&outcomes, threshold)) {
+        Ok(()) => println!("\n📄 Wrote JSON report to {}", report_path),
+        Err(e) => eprintln!("Could not write {}: {}", report_path, e),
+    }
+}
+
+// Global flags that take a value, and those that don't. The first argument
+// that is neither (nor a flag's value) names the subcommand.
+const GLOBAL_VALUE_FLAGS: &[&str] = &["--extra-words", "--config", "--set", "--budget", "--model", "--lang"];
+const GLOBAL_SWITCHES: &[&str] = &["--fold-accents", "--resume"];
+
+fn subcommand_index(args: &[String]) -> Option<usize> {
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            flag if GLOBAL_VALUE_FLAGS.contains(&flag) => i += 2,
+            flag if GLOBAL_SWITCHES.contains(&flag) => i += 1,
+            _ => return Some(i),
+        }
+    }
+    None
+}
+
+// Splits the arguments into the global flags and the subcommand with its own
+// arguments.
+fn split_global_flags(mut args: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let command = args.split_off(subcommand_index(&args).unwrap_or(args.len()));
+    (args, command)
+}
+
+/// Runs the command line: global flags, then a subcommand or the menu game.
+pub fn run() {
+    install_interrupt_handler();
+
+    // Global flags only count before the subcommand; everything from the
+    // subcommand on is left to it (`enigma` has its own `--model`, say).
+    let (mut args, command_args) = split_global_flags(std::env::args().skip(1).collect());
+
+    // `--model FILE` scores with a model from the `train` command instead of
+    // the built-in English tables (unless `--lang` says otherwise).
+    let model_path = args.iter().position(|a| a == "--model").map(|i| {
+        let path = args.get(i + 1).cloned().unwrap_or_default();
+        args.drain(i..(i + 2).min(args.len()));
+        path
+    });
+
+    // `--fold-accents` folds accented letters (é -> e) in the ciphertext,
+    // but not in paths or flag values.
+    let fold = args.iter().any(|a| a == "--fold-accents");
+    args.retain(|a| a != "--fold-accents");
+
+    // `--extra-words FILE` adds a word list to the dictionary used for
+    // validation.
+    let mut extra_words = Vec::new();
+    if let Some(i) = args.iter().position(|a| a == "--extra-words") {
+        let path = args.get(i + 1).cloned().unwrap_or_default();
+        match load_extra_words(&path) {
+            Ok(words) => extra_words = words,
+            Err(e) => {
+                eprintln!("Could not read extra words: {}", e);
+                return;
+            }
+        }
+        args.drain(i..(i + 2).min(args.len()));
+    }
+
+    // Search ranges and time budgets come from vigenere_bruteforce.toml (or
+    // `--config FILE`), then `--set section.key=value` and `--budget SECS`.
+    let mut config = SearchConfig::default();
+    let config_path = match args.iter().position(|a| a == "--config") {
+        Some(i) => {
+            let path = args.get(i + 1).cloned().unwrap_or_default();
+            args.drain(i..(i + 2).min(args.len()));
+            Some(path)
+        }
+        None => Some(CONFIG_PATH.to_string()).filter(|p| std::path::Path::new(p).exists()),
+    };
+    if let Some(path) = config_path
+        && let Err(e) = config.load(&path)
+    {
+        eprintln!("Could not load config: {}", e);
+        return;
+    }
+    while let Some(i) = args.iter().position(|a| a == "--set" || a == "--budget") {
+        let value = args.get(i + 1).cloned().unwrap_or_default();
+        let setting = if args[i] == "--budget" { format!("budget={}", value) } else { value };
+        if let Err(e) = config.set_from_arg(&setting) {
+            eprintln!("Bad setting '{}': {}", setting, e);
+            return;
+        }
+        args.drain(i..(i + 2).min(args.len()));
+    }
+
+    // `--resume` picks a sweep back up from the checkpoint file.
+    let mut resume = None;
+    if let Some(i) = args.iter().position(|a| a == "--resume") {
+        match load_checkpoint(CHECKPOINT_PATH) {
+            Ok(checkpoint) => resume = Some(checkpoint),
+            Err(e) => {
+                eprintln!("Could not resume: {}", e);
+                return;
+            }
+        }
+        args.remove(i);
+    }
+
+    let model = match model_path.map(|path| load_model(&path)) {
+        Some(Ok(model)) => Some(model),
+        Some(Err(e)) => {
+            eprintln!("Could not load model: {}", e);
+            return;
+        }
+        None => None,
+    };
+
+    // `--lang en|fr|de|es|it|auto` picks the scoring language; a loaded
+    // model is the default.
+    let mut language = LanguageChoice::Fixed(if model.is_some() { Language::Custom } else { Language::English });
+    if let Some(i) = args.iter().position(|a| a == "--lang") {
+        let value = args.get(i + 1).cloned().unwrap_or_default();
+        language = match value.as_str() {
+            "auto" => LanguageChoice::Auto,
+            other => match Language::parse(other) {
+                Some(lang) => LanguageChoice::Fixed(lang),
+                None => {
+                    eprintln!("Unknown language '{}' (expected en, fr, de, es, it or auto)", value);
+                    return;
+                }
+            },
+        };
+        args.drain(i..(i + 2).min(args.len()));
+    }
+
+    let printer = StatusPrinter::new();
+    let ctx = Context {
+        scorer: Scorer::new(language, model, &extra_words),
+        config,
+        report: Box::new(move |status| printer.print(status)),
+        fold,
+    };
+    let ciphertext = ctx.input(CIPHERTEXT);
+    let args = command_args;
+
+    match args.first().map(String::as_str) {
+        Some("xor") => return run_xor_command(&ctx, &args[1..]),
+        Some("pipeline") => return run_pipeline_command(&ctx, &args[1..]),
+        Some("runkey") => return run_running_key_command(&ctx, &args[1..]),
+        Some("book") => return run_book_command(&ctx, &args[1..]),
+        Some("enigma") => return run_enigma_command(&ctx, &args[1..]),
+        Some("homophonic") => return run_homophonic_command(&ctx, &args[1..]),
+        Some("checkerboard") => return run_checkerboard_command(&ctx, &args[1..]),
+        Some("chaocipher") => return run_chaocipher_command(&ctx, &args[1..]),
+        Some("solitaire") => return run_solitaire_command(&ctx, &args[1..]),
+        Some("shift") => return run_shift_command(&ctx, &args[1..]),
+        Some("keyword") => return run_keyword_command(&ctx, &args[1..]),
+        Some("train") => return run_train_command(&args[1..]),
+        Some("batch") => return run_batch_command(&ctx, &args[1..]),
+        _ => {}
+    }
+
+    println!("\n╔════════════════════════════════════════╗");
+    println!("║         CRYPTO BREAKER GAME           ║");
+    println!("╚════════════════════════════════════════╝\n");
+    println!("Ciphertext to crack:\n");
+    println!("  \"{}\"\n", ciphertext);
+    
+    loop {
+        let choice = match resume.as_ref().map(Checkpoint::menu_choice) {
+            Some(choice) => {
+                println!("\n♻️  Resuming from {}...", CHECKPOINT_PATH);
+                choice
+            }
+            None => get_user_choice(),
+        };
+        let session = ctx.session().with_checkpoint(CHECKPOINT_PATH, choice, resume.take());
+        let mut top_n = TopN::new(5);
+        
+        if choice == 0 {
+            println!("\n🚀 Starting full brute force attack on all ciphers...");
+            crack_with_layers(&session, &ciphertext, &mut top_n, |text, top| crack_all_ciphers(&session, text, top));
+        } else {
+            println!("\n🎯 Testing cipher #{}...", choice);
+            crack_with_layers(&session, &ciphertext, &mut top_n, |text, top| {
+                run_cipher(&session, choice, text, top, ctx.config.budget_for(choice))
+            });
+        }
+        
+        display_results(&ctx, &top_n);
+        clear_interrupt();
+        
+        // Ask if user wants to try another cipher
+        println!("{}════════════════════════════════════════════════", "═".repeat(25));
+        print!("\nTry another cipher? (y/n): ");
+        io::stdout().flush().unwrap();
+        
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).unwrap();
+        
+        if input.trim().to_lowercase() != "y" {
+            println!("\n👋 Thanks for playing!");
+            break;
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn subcommand_follows_global_flags() {
+        assert_eq!(subcommand_index(&args("enigma --model M4")), Some(0));
+        assert_eq!(subcommand_index(&args("--budget 10 enigma --model M4")), Some(2));
+        assert_eq!(subcommand_index(&args("--fold-accents --lang fr --model fr.vblm enigma")), Some(5));
+        assert_eq!(subcommand_index(&args("--resume --set vigenere.keys=3-5")), None);
+    }
+
+    #[test]
+    fn subcommand_keeps_its_own_flags() {
+        let (globals, command) = split_global_flags(args("--lang fr shift --lang de --set budget=5"));
+        assert_eq!(globals, args("--lang fr"));
+        assert_eq!(command, args("shift --lang de --set budget=5"));
+        let (globals, command) = split_global_flags(args("--fold-accents --budget 10"));
+        assert_eq!(globals, args("--fold-accents --budget 10"));
+        assert!(command.is_empty());
+    }
+
+    #[test]
+    fn fold_accents_only_touches_input_text() {
+        let ctx = Context { scorer: Scorer::default(), config: SearchConfig::default(), report: Box::new(|_| {}), fold: true };
+        assert_eq!(ctx.input("Élève café"), "Eleve cafe");
+        let plain = Context { fold: false, ..ctx };
+        assert_eq!(plain.input("café"), "café");
+    }
+}
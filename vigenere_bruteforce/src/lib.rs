@@ -0,0 +1,6544 @@
+//! Classical cipher breaking: decryptors for Caesar, Vigenère, Playfair,
+//! Enigma and friends, English (and other language) scorers, a bounded
+//! [`TopN`] result list and brute-force solvers that fill it.
+//!
+//! The solvers take a [`Session`]: the [`Scorer`] (language, trained model,
+//! dictionary), the [`SearchConfig`], an optional stop flag and a callback for
+//! [`Status`] updates. Nothing is printed and no settings are global.
+//!
+//! ```
+//! use vigenere_bruteforce::{crack_specific_cipher, decrypt_vigenere, Scorer, SearchConfig, Session, TopN};
+//!
+//! assert_eq!(decrypt_vigenere("Rijvs", &[10, 4, 24]).unwrap(), "Hello");
+//!
+//! let (scorer, config) = (Scorer::default(), SearchConfig::default());
+//! let session = Session::new(&scorer, &config);
+//! let mut top_n = TopN::new(5);
+//! crack_specific_cipher(&session, 1, "Wkh vhfuhw lv vdih", &mut top_n);
+//! assert_eq!(top_n.best_result().unwrap().plaintext_full, "The secret is safe");
+//! ```
+
+use std::collections::{HashMap, BinaryHeap};
+use std::cmp::Ordering;
+use std::io;
+
+/// Common English words; also the default keyword and passphrase list.
+pub const COMMON_WORDS: &[&str] = &[
+    "the","and","to","of","in","is","it","you","that","he","was","for","on","are",
+    "as","with","his","they","be","at","one","have","this","from","or","had","by",
+    "but","not","we","my","so","if","me","your","what","all","can","no","about",
+    "will","would","there","their","which","when","make","like","time","very",
+    "come","just","know","take","people","year","work","back","call","feel","find",
+    "give","good","hand","high","keep","last","life","live","mean","need","next",
+    "open","over","part","play","said","same","seem","such","tell","than","them",
+    "then","these","thus","want","well","were","word","write","years","ancient",
+    "library","stood","majestically","hillside","weathered","stone","walls",
+    "holding","countless","secrets","within","scholars","across","kingdom",
+    "journey","months","access","rare","manuscripts","precious","knowledge","head",
+    "librarian","guarded","treasures","fiercely","allowing","dedicated",
+    "researchers","study","carefully"
+];
+
+const FREQ: &str = "etaoinshrdlu";
+
+/// Number of ciphers on the menu; menu numbers run from 1 to this.
+pub const CIPHER_COUNT: usize = 21;
+
+// Cipher types from simplest to most involved, used to pick which of several
+// results with the same plaintext to keep. Unlisted types come after these.
+const CIPHER_SIMPLICITY: &[&str] = &[
+    "Encoding", "Reverse", "Atbash", "ROT13", "Caesar", "Rail Fence", "Columnar",
+    "Affine", "Keyword", "Vigenère", "Beaufort", "Hybrid", "Pipeline",
+];
+
+// How many equivalent cipher/key labels a result keeps.
+const MAX_EQUIVALENTS: usize = 5;
+
+/// One candidate decryption: the cipher and key that produced it, its
+/// score and the plaintext.
+#[derive(Clone, Eq, PartialEq)]
+pub struct CrackResult {
+    /// Higher is better; comparable within one language.
+    pub score: i32,
+    /// Cipher family, e.g. `Vigenère`.
+    pub cipher_type: String,
+    /// The key or settings, as text.
+    pub params: String,
+    /// The first 80 characters of the plaintext.
+    pub plaintext_preview: String,
+    /// The whole decryption.
+    pub plaintext_full: String,
+    /// Other cipher/key combinations that gave exactly this plaintext.
+    pub equivalents: Vec<String>,
+}
+
+impl CrackResult {
+    fn label(&self) -> String {
+        format!("{} {}", self.cipher_type, self.params)
+    }
+
+    // Sort key for "simplest": cipher family, then shortest parameters.
+    fn simplicity(&self) -> (usize, usize, &str) {
+        let rank = CIPHER_SIMPLICITY
+            .iter()
+            .position(|t| self.cipher_type.starts_with(t))
+            .unwrap_or(CIPHER_SIMPLICITY.len());
+        (rank, self.params.len(), &self.params)
+    }
+
+    // Folds an equivalent result in, keeping whichever is simpler as the
+    // headline and listing the other.
+    fn absorb(&mut self, mut other: CrackResult) {
+        if other.simplicity() < self.simplicity() {
+            std::mem::swap(self, &mut other);
+        }
+        for label in std::iter::once(other.label()).chain(other.equivalents) {
+            if self.equivalents.len() < MAX_EQUIVALENTS && !self.equivalents.contains(&label) {
+                self.equivalents.push(label);
+            }
+        }
+    }
+}
+
+impl Ord for CrackResult {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score.cmp(&other.score)
+    }
+}
+
+impl PartialOrd for CrackResult {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Keeps the `limit` highest-scoring results. The heap is a min-heap so the
+/// weakest kept result is the one on top to be evicted.
+pub struct TopN {
+    heap: BinaryHeap<std::cmp::Reverse<CrackResult>>,
+    limit: usize,
+}
+
+impl TopN {
+    /// An empty list that keeps at most `limit` results.
+    pub fn new(limit: usize) -> Self {
+        TopN {
+            heap: BinaryHeap::new(),
+            limit,
+        }
+    }
+
+    /// Whether a result with this score could be kept, so callers can skip
+    /// building strings for candidates that would be thrown away. A tie with
+    /// the weakest kept score counts, since the same plaintext always scores
+    /// the same and may still be merged in as an equivalent.
+    pub fn accepts(&self, score: i32) -> bool {
+        self.heap.len() < self.limit || self.heap.peek().is_some_and(|weakest| score >= weakest.0.score)
+    }
+
+    /// A result whose plaintext is already kept is merged into that entry
+    /// (whatever its score) instead of taking a second slot.
+    pub fn insert(&mut self, result: CrackResult) {
+        if self.heap.iter().any(|r| r.0.plaintext_full == result.plaintext_full) {
+            let mut kept = std::mem::take(&mut self.heap).into_vec();
+            let existing = kept.iter_mut().find(|r| r.0.plaintext_full == result.plaintext_full).unwrap();
+            let score = existing.0.score.max(result.score);
+            existing.0.absorb(result);
+            existing.0.score = score;
+            self.heap = kept.into();
+            return;
+        }
+        if self.heap.len() == self.limit {
+            match self.heap.peek() {
+                Some(weakest) if result.score > weakest.0.score => self.heap.pop(),
+                _ => return,
+            };
+        }
+        self.heap.push(std::cmp::Reverse(result));
+    }
+
+    /// Inserts a result built from its parts, previewing the first 80
+    /// characters of the plaintext.
+    pub fn insert_lightweight(&mut self, score: i32, cipher_type: String, params: String, plaintext: &str) {
+        let preview: String = plaintext.chars().take(80).collect();
+        self.insert(CrackResult {
+            score,
+            cipher_type,
+            params,
+            plaintext_preview: preview,
+            plaintext_full: plaintext.to_string(),
+            equivalents: Vec::new(),
+        });
+    }
+
+    /// The kept results, best first.
+    pub fn into_sorted_vec(self) -> Vec<CrackResult> {
+        let mut vec: Vec<CrackResult> = self.heap.into_iter().map(|r| r.0).collect();
+        vec.sort_by_key(|r| std::cmp::Reverse(r.score));
+        vec
+    }
+
+    /// Copies of the kept results, best first.
+    pub fn sorted_results(&self) -> Vec<CrackResult> {
+        let mut vec: Vec<CrackResult> = self.heap.iter().map(|r| r.0.clone()).collect();
+        vec.sort_by_key(|r| std::cmp::Reverse(r.score));
+        vec
+    }
+
+    /// The highest-scoring result, if any.
+    pub fn best_result(&self) -> Option<&CrackResult> {
+        self.heap.iter().map(|r| &r.0).max_by_key(|r| r.score)
+    }
+}
+
+#[cfg(test)]
+mod top_n_tests {
+    use super::*;
+
+    fn filled(limit: usize) -> TopN {
+        let mut top_n = TopN::new(limit);
+        for (score, plain) in [(50, "alpha"), (40, "bravo"), (30, "charlie")] {
+            top_n.insert_lightweight(score, "Caesar".to_string(), format!("shift {}", score), plain);
+        }
+        top_n
+    }
+
+    #[test]
+    fn keeps_the_best_and_evicts_the_weakest() {
+        let mut top_n = filled(3);
+        top_n.insert_lightweight(45, "ROT13".to_string(), "ROT13".to_string(), "delta");
+        let scores: Vec<i32> = top_n.sorted_results().iter().map(|r| r.score).collect();
+        assert_eq!(scores, [50, 45, 40]);
+        top_n.insert_lightweight(10, "ROT13".to_string(), "ROT13".to_string(), "echo");
+        assert_eq!(top_n.sorted_results().len(), 3);
+        assert!(TopN::new(0).sorted_results().is_empty());
+    }
+
+    #[test]
+    fn merges_same_plaintext_even_below_the_cut_off() {
+        let mut top_n = filled(3);
+        assert!(top_n.accepts(30));
+        top_n.insert_lightweight(5, "Vigenère".to_string(), "key: abab".to_string(), "charlie");
+        let results = top_n.sorted_results();
+        assert_eq!(results.len(), 3);
+        let charlie = results.iter().find(|r| r.plaintext_full == "charlie").unwrap();
+        assert_eq!(charlie.score, 30);
+        assert_eq!(charlie.equivalents, ["Vigenère key: abab"]);
+    }
+
+    #[test]
+    fn simplest_cipher_heads_a_merged_result() {
+        let mut top_n = TopN::new(5);
+        top_n.insert_lightweight(60, "Vigenère".to_string(), "key: d".to_string(), "hello");
+        top_n.insert_lightweight(60, "Caesar".to_string(), "shift 3".to_string(), "hello");
+        let best = top_n.best_result().unwrap();
+        assert_eq!(best.label(), "Caesar shift 3");
+        assert_eq!(best.equivalents, ["Vigenère key: d"]);
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::*;
+
+    #[test]
+    fn loads_a_config_file() {
+        let path = std::env::temp_dir().join(format!("config_{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "budget = 600 # seconds\nscheduler = \"menu\"\n\n[vigenere]\nkey_lengths = [2, 7]\nbudget = 30\n\
+             [playfair]\nkeys = [\"monarchy\", \"a#b\"]\n",
+        )
+        .unwrap();
+        let mut config = SearchConfig::default();
+        let loaded = config.load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        loaded.unwrap();
+
+        assert_eq!(config.vigenere_keys, 2..=7);
+        assert!(config.scheduler == Scheduler::Menu);
+        assert_eq!(config.playfair_keys, ["monarchy", "a#b"]);
+        assert_eq!(config.budget_for(4), Some(std::time::Duration::from_secs(30)));
+        assert_eq!(config.budget_for(1), Some(std::time::Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn parses_settings_from_arguments() {
+        let mut config = SearchConfig::default();
+        config.set_from_arg("rail_fence.rails=[3, 9]").unwrap();
+        config.set_from_arg("columnar.columns=4").unwrap();
+        config.set_from_arg(" success_confidence = 90").unwrap();
+        config.set_from_arg("enigma.budget=2.5").unwrap();
+        assert_eq!(config.rails, 3..=9);
+        assert_eq!(config.columns, 4..=4);
+        assert_eq!(config.success_confidence(), 90);
+        assert_eq!(config.budget_for(17), Some(std::time::Duration::from_millis(2500)));
+        assert_eq!(config.budget_for(1), None);
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        let mut config = SearchConfig::default();
+        assert_eq!(config.set_from_arg("colour=3").unwrap_err(), "unknown setting 'colour'");
+        assert_eq!(config.set_from_arg("vigenere.depth=3").unwrap_err(), "unknown setting 'vigenere.depth'");
+        assert_eq!(config.set_from_arg("lemon.budget=3").unwrap_err(), "unknown cipher 'lemon'");
+        assert!(config.set_from_arg("budget").is_err());
+        assert!(config.set_from_arg("budget=-1").is_err());
+        assert!(config.set_from_arg("budget=soon").is_err());
+        assert!(config.set_from_arg("vigenere.key_lengths=[5, 2]").is_err());
+        assert!(config.set_from_arg("vigenere.key_lengths=[1, 13]").is_err());
+        assert!(config.set_from_arg("scheduler=\"random\"").is_err());
+        assert!(config.set_from_arg("playfair.keys=[1, 2]").is_err());
+
+        let path = std::env::temp_dir().join(format!("bad_config_{}.toml", std::process::id()));
+        std::fs::write(&path, "[vigenere]\nkey_lengths = [1, 4]\nkey_length = 3\n").unwrap();
+        let loaded = config.load(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.unwrap_err().ends_with("line 3: unknown setting 'vigenere.key_length'"));
+    }
+
+    #[test]
+    fn budget_stops_a_run() {
+        let scorer = Scorer::default();
+        let mut config = SearchConfig::default();
+        config.set_from_arg("vigenere.budget=0").unwrap();
+        let lines = std::cell::RefCell::new(Vec::new());
+        let report = |status: Status| {
+            if let Status::Message(line) = status {
+                lines.borrow_mut().push(line);
+            }
+        };
+        let session = Session::new(&scorer, &config).with_reporter(&report);
+
+        let started = std::time::Instant::now();
+        run_cipher(&session, 4, "Rijvs uyvjn", &mut TopN::new(5), config.budget_for(4));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        assert!(lines.borrow().iter().any(|l| l.contains("Time budget of 0s used up for vigenere")), "{:?}", lines.borrow());
+    }
+}
+
+// ========== DICTIONARY VALIDATION ==========
+
+// About 66k lowercase English words, one per line; see data/english_words.txt.
+// Contractions are listed without the apostrophe ("dont").
+const ENGLISH_WORDS: &str = include_str!("../data/english_words.txt");
+
+// Bits 0-25 mark which letters have a child; bit 31 marks the end of a word.
+// A node's children are stored consecutively from `first_child` in letter
+// order, so the child for a letter is found by counting the lower bits.
+#[derive(Clone, Copy)]
+struct DictNode {
+    first_child: u32,
+    mask: u32,
+}
+
+const DICT_TERMINAL: u32 = 1 << 31;
+
+impl DictNode {
+    fn child(&self, letter: u8) -> Option<usize> {
+        let index = letter.to_ascii_lowercase().wrapping_sub(b'a');
+        if index >= 26 {
+            return None;
+        }
+        let bit = 1u32 << index;
+        if self.mask & bit == 0 {
+            return None;
+        }
+        Some(self.first_child as usize + (self.mask & (bit - 1)).count_ones() as usize)
+    }
+}
+
+// A letter trie in one flat array of 8-byte nodes; a lookup costs a mask
+// test and a popcount per character.
+struct Dictionary {
+    nodes: Vec<DictNode>,
+}
+
+impl Dictionary {
+    fn build<'a>(words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut words: Vec<&[u8]> = words
+            .into_iter()
+            .map(str::as_bytes)
+            .filter(|w| !w.is_empty() && w.iter().all(u8::is_ascii_lowercase))
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+
+        let mut nodes = vec![DictNode { first_child: 0, mask: 0 }];
+        // Breadth-first, so the children of a node are allocated together.
+        // Each entry is (node, range of words sharing its prefix, depth).
+        let mut queue = std::collections::VecDeque::from([(0usize, 0usize, words.len(), 0usize)]);
+        while let Some((node, mut lo, hi, depth)) = queue.pop_front() {
+            if lo < hi && words[lo].len() == depth {
+                nodes[node].mask |= DICT_TERMINAL;
+                lo += 1;
+            }
+            nodes[node].first_child = nodes.len() as u32;
+            while lo < hi {
+                let letter = words[lo][depth];
+                let end = lo + words[lo..hi].partition_point(|w| w[depth] == letter);
+                nodes[node].mask |= 1 << (letter - b'a');
+                queue.push_back((nodes.len(), lo, end, depth + 1));
+                nodes.push(DictNode { first_child: 0, mask: 0 });
+                lo = end;
+            }
+        }
+        Dictionary { nodes }
+    }
+
+    // Case-insensitive; anything other than a letter is a miss.
+    fn contains(&self, word: impl IntoIterator<Item = u8>) -> bool {
+        let mut node = self.nodes[0];
+        for b in word {
+            match node.child(b) {
+                Some(child) => node = self.nodes[child],
+                None => return false,
+            }
+        }
+        node.mask & DICT_TERMINAL != 0
+    }
+
+    // Lengths of every dictionary word that `text` starts with, shortest first.
+    fn prefix_lengths(&self, text: &[u8]) -> Vec<usize> {
+        let mut lengths = Vec::new();
+        let mut node = self.nodes[0];
+        for (i, &b) in text.iter().enumerate() {
+            match node.child(b) {
+                Some(child) => node = self.nodes[child],
+                None => break,
+            }
+            if node.mask & DICT_TERMINAL != 0 {
+                lengths.push(i + 1);
+            }
+        }
+        lengths
+    }
+}
+
+fn dictionary() -> &'static Dictionary {
+    static DICT: std::sync::OnceLock<Dictionary> = std::sync::OnceLock::new();
+    DICT.get_or_init(|| Dictionary::build(ENGLISH_WORDS.lines()))
+}
+
+fn common_words() -> &'static Dictionary {
+    static COMMON: std::sync::OnceLock<Dictionary> = std::sync::OnceLock::new();
+    COMMON.get_or_init(|| Dictionary::build(COMMON_WORDS.iter().copied()))
+}
+
+/// Reads a word list for [`Scorer::new`]: whitespace-separated words,
+/// lowercased, with anything but ASCII letters dropped.
+pub fn load_extra_words(path: &str) -> std::result::Result<Vec<String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(text
+        .split_whitespace()
+        .map(|w| w.chars().filter(|c| c.is_ascii_alphabetic()).collect::<String>().to_ascii_lowercase())
+        .filter(|w| !w.is_empty())
+        .collect())
+}
+
+// Punctuation that can sit at either end of a word or join two words.
+const WORD_EDGE_PUNCTUATION: &[u8] = b".,;:!?\"'()[]";
+
+/// Percentage of words found in the dictionary. Words are the
+/// whitespace-separated tokens with edge punctuation trimmed and split at
+/// hyphens; apostrophes are dropped so "don't" is looked up as "dont".
+/// Numbers are skipped, and a token with any other symbol in it counts as a
+/// miss, so symbol soup with the odd letter in it doesn't pass as English.
+pub fn is_valid_english(text: &[u8]) -> i32 {
+    valid_word_percent(text, dictionary())
+}
+
+fn valid_word_percent(text: &[u8], dict: &Dictionary) -> i32 {
+    let (mut words, mut valid_count) = (0, 0);
+    for token in text.split(u8::is_ascii_whitespace) {
+        let start = token.iter().position(|b| !WORD_EDGE_PUNCTUATION.contains(b)).unwrap_or(token.len());
+        let end = token.iter().rposition(|b| !WORD_EDGE_PUNCTUATION.contains(b)).map_or(start, |i| i + 1);
+        let token = &token[start..end];
+        if token.iter().all(u8::is_ascii_digit) {
+            continue;
+        }
+        for w in token.split(|&b| b == b'-').filter(|w| !w.is_empty()) {
+            words += 1;
+            if w.iter().all(|&b| b.is_ascii_alphabetic() || b == b'\'') {
+                valid_count += dict.contains(w.iter().copied().filter(|&b| b != b'\'')) as i32;
+            }
+        }
+    }
+
+    if words == 0 {
+        return 0;
+    }
+    (valid_count * 100) / words
+}
+
+#[cfg(test)]
+mod dictionary_tests {
+    use super::*;
+
+    #[test]
+    fn symbol_laden_tokens_are_misses() {
+        assert_eq!(is_valid_english(b"the dog ran"), 100);
+        assert_eq!(is_valid_english(b"\"The cat,\" said the man."), 100);
+        assert_eq!(is_valid_english(b"the c@t ran"), 66);
+        assert_eq!(is_valid_english(b"#$% a&b *x* the"), 25);
+        // Numbers are skipped rather than counted.
+        assert_eq!(is_valid_english(b"the 42 cats"), 100);
+    }
+
+    #[test]
+    fn extra_words_file_is_read_and_accepted() {
+        let path = std::env::temp_dir().join(format!("extra_words_{}.txt", std::process::id()));
+        std::fs::write(&path, "Zorblax, QUUXLY\n  frobnitz! 42\n").unwrap();
+        let words = load_extra_words(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(words, ["zorblax", "quuxly", "frobnitz"]);
+
+        let dict = Dictionary::build(ENGLISH_WORDS.lines().chain(words.iter().map(String::as_str)));
+        assert_eq!(valid_word_percent(b"the zorblax frobnitz", &dict), 100);
+        assert_eq!(is_valid_english(b"the zorblax frobnitz"), 33);
+        assert!(load_extra_words("/nonexistent/extra_words.txt").is_err());
+    }
+}
+
+// ========== WORD SEGMENTATION ==========
+
+// Counts for the 5000 most frequent dictionary words; see
+// data/english_word_counts.txt.
+const ENGLISH_WORD_COUNTS: &str = include_str!("../data/english_word_counts.txt");
+
+// Longest run of letters treated as a single unknown word.
+const MAX_UNKNOWN_WORD: usize = 20;
+
+struct WordFrequencies {
+    counts: HashMap<&'static str, f64>,
+    total: f64,
+}
+
+impl WordFrequencies {
+    // Dictionary words missing from the counts get half a sighting. Unknown
+    // words lose a factor of a hundred per letter, so they only win when
+    // nothing in the dictionary fits.
+    fn log_prob(&self, word: &str, known: bool) -> f64 {
+        if let Some(&count) = self.counts.get(word) {
+            (count / self.total).ln()
+        } else if known {
+            (0.5 / self.total).ln()
+        } else {
+            10f64.ln() - self.total.ln() - word.len() as f64 * 100f64.ln()
+        }
+    }
+}
+
+fn word_frequencies() -> &'static WordFrequencies {
+    static FREQS: std::sync::OnceLock<WordFrequencies> = std::sync::OnceLock::new();
+    FREQS.get_or_init(|| {
+        let counts: HashMap<&'static str, f64> = ENGLISH_WORD_COUNTS
+            .lines()
+            .filter_map(|line| {
+                let (word, count) = line.split_once(' ')?;
+                Some((word, count.trim().parse().ok()?))
+            })
+            .collect();
+        let total = counts.values().sum();
+        WordFrequencies { counts, total }
+    })
+}
+
+/// Splits run-together letters into the most probable sequence of words
+/// (Viterbi over a unigram model), e.g. "meetmeatthelibrary" becomes
+/// "meet me at the library". Anything that is not an ASCII letter is dropped.
+pub fn segment_words(text: &str) -> String {
+    segment_with(text, dictionary())
+}
+
+fn segment_with(text: &str, dict: &Dictionary) -> String {
+    let letters: Vec<u8> = text
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_lowercase())
+        .collect();
+    let n = letters.len();
+    let freqs = word_frequencies();
+    let word = |from: usize, to: usize| std::str::from_utf8(&letters[from..to]).unwrap();
+
+    // best[i] is the log probability of the best split of letters[..i];
+    // start[i] is where its last word begins.
+    let mut best = vec![f64::NEG_INFINITY; n + 1];
+    let mut start = vec![0usize; n + 1];
+    best[0] = 0.0;
+    for i in 0..n {
+        let mut relax = |end: usize, log_prob: f64| {
+            if best[i] + log_prob > best[end] {
+                best[end] = best[i] + log_prob;
+                start[end] = i;
+            }
+        };
+        for len in dict.prefix_lengths(&letters[i..]) {
+            relax(i + len, freqs.log_prob(word(i, i + len), true));
+            // The word list holds few plurals, so a dictionary word plus "s"
+            // counts as a known word too; otherwise "hills" comes out as
+            // "hill s".
+            if letters.get(i + len) == Some(&b's') {
+                relax(i + len + 1, freqs.log_prob(word(i, i + len + 1), true));
+            }
+        }
+        for end in (i + 1)..=(i + MAX_UNKNOWN_WORD).min(n) {
+            relax(end, freqs.log_prob(word(i, end), false));
+        }
+    }
+
+    let mut words = Vec::new();
+    let mut end = n;
+    while end > 0 {
+        words.push(word(start[end], end));
+        end = start[end];
+    }
+    words.reverse();
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod segment_tests {
+    use super::*;
+
+    #[test]
+    fn splits_run_together_words() {
+        assert_eq!(segment_words("meetmeatthelibrary"), "meet me at the library");
+        assert_eq!(segment_words("wearediscoveredfleeatonce"), "we are discovered flee at once");
+        assert_eq!(segment_words("fourscoreandsevenyearsago"), "four score and seven years ago");
+    }
+
+    #[test]
+    fn keeps_plurals_whole() {
+        assert_eq!(segment_words("thehillsarealive"), "the hills are alive");
+        assert_eq!(segment_words("thesunsetsinthewest"), "the sun sets in the west");
+    }
+
+    #[test]
+    fn drops_everything_but_letters() {
+        assert_eq!(segment_words("Hello, World! 42"), "hello world");
+        assert_eq!(segment_words("xqzvv"), "xqzvv");
+        assert_eq!(segment_words(""), "");
+    }
+}
+
+// ========== ERRORS ==========
+
+/// A key or input the cipher can't work with at all, as opposed to a valid
+/// key that merely decrypts to nonsense.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CipherError {
+    EmptyKey,
+    KeyOutOfRange { value: usize, modulus: usize },
+    NotInvertible { a: usize, modulus: usize },
+    InvalidKey(String),
+    InvalidInput(String),
+}
+
+/// What the decryption functions return when a key can be rejected.
+pub type CipherResult<T> = std::result::Result<T, CipherError>;
+
+impl std::fmt::Display for CipherError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CipherError::EmptyKey => write!(f, "key is empty"),
+            CipherError::KeyOutOfRange { value, modulus } => {
+                write!(f, "key value {} is out of range for an alphabet of {} symbols", value, modulus)
+            }
+            CipherError::NotInvertible { a, modulus } => {
+                write!(f, "a={} has no inverse modulo {} (they must be coprime)", a, modulus)
+            }
+            CipherError::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            CipherError::InvalidInput(reason) => write!(f, "invalid input: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CipherError {}
+
+// Shift keys must be non-empty with every value inside the alphabet.
+fn check_shift_key<T: Copy + Into<usize>>(key: &[T], modulus: usize) -> CipherResult<()> {
+    if key.is_empty() {
+        return Err(CipherError::EmptyKey);
+    }
+    match key.iter().map(|&k| k.into()).find(|&k| k >= modulus) {
+        Some(value) => Err(CipherError::KeyOutOfRange { value, modulus }),
+        None => Ok(()),
+    }
+}
+
+// ========== TEXT NORMALIZATION ==========
+
+// Accented Latin letters and the ASCII they fold to. Uppercase input is
+// looked up through its lowercase form and keeps its case.
+const ACCENT_FOLDS: &[(&str, &str)] = &[
+    ("àáâãäåāăą", "a"),
+    ("çćĉċč", "c"),
+    ("ďđð", "d"),
+    ("èéêëēĕėęě", "e"),
+    ("ĝğġģ", "g"),
+    ("ĥħ", "h"),
+    ("ìíîïĩīĭįı", "i"),
+    ("ĵ", "j"),
+    ("ķ", "k"),
+    ("ĺļľŀł", "l"),
+    ("ñńņňŉ", "n"),
+    ("òóôõöøōŏő", "o"),
+    ("ŕŗř", "r"),
+    ("śŝşšș", "s"),
+    ("ţťŧț", "t"),
+    ("ùúûüũūŭůűų", "u"),
+    ("ŵ", "w"),
+    ("ýÿŷ", "y"),
+    ("źżž", "z"),
+    ("æ", "ae"),
+    ("œ", "oe"),
+    ("ß", "ss"),
+    ("þ", "th"),
+];
+
+/// Replaces accented letters with plain ASCII (`é` -> `e`, `ß` -> `ss`);
+/// everything else is kept.
+pub fn fold_accents(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii() {
+            out.push(c);
+            continue;
+        }
+        let lower = c.to_lowercase().next().unwrap_or(c);
+        match ACCENT_FOLDS.iter().find(|(accented, _)| accented.contains(lower)) {
+            Some((_, plain)) if c.is_uppercase() => out.push_str(&plain.to_ascii_uppercase()),
+            Some((_, plain)) => out.push_str(plain),
+            None => out.push(c),
+        }
+    }
+    out
+}
+
+// ========== DECRYPTION FUNCTIONS ==========
+
+/// Shifts each ASCII letter back by `shift`, keeping case. Other
+/// characters pass through.
+pub fn decrypt_caesar(text: &str, shift: u8) -> String {
+    let shift = shift % 26;
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                ((c as u8 - b'a' + 26 - shift) % 26 + b'a') as char
+            } else if c.is_ascii_uppercase() {
+                ((c as u8 - b'A' + 26 - shift) % 26 + b'A') as char
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Caesar with a shift of 13, which is its own inverse.
+pub fn decrypt_rot13(text: &str) -> String {
+    decrypt_caesar(text, 13)
+}
+
+/// Mirrors the alphabet (a <-> z, b <-> y, ...).
+pub fn decrypt_atbash(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                (b'z' - (c as u8 - b'a')) as char
+            } else if c.is_ascii_uppercase() {
+                (b'Z' - (c as u8 - b'A')) as char
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Vigenère with a repeating key of shifts (0-25, a = 0). The key only
+/// advances on letters.
+pub fn decrypt_vigenere(text: &str, key: &[u8]) -> CipherResult<String> {
+    check_shift_key(key, 26)?;
+    let mut out = String::with_capacity(text.len());
+    let mut k = 0;
+
+    for c in text.chars() {
+        if c.is_ascii_alphabetic() {
+            let shift = key[k % key.len()];
+            let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+            let d = ((c as u8 - base + 26 - shift) % 26) + base;
+            out.push(d as char);
+            k += 1;
+        } else {
+            out.push(c);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Undoes a rail fence written in a zigzag over `rails` rails.
+pub fn decrypt_rail_fence(text: &str, rails: usize) -> String {
+    if rails <= 1 {
+        return text.to_string();
+    }
+
+    let cipher_chars: Vec<char> = text.chars().collect();
+    let n = cipher_chars.len();
+    let mut fence: Vec<Vec<usize>> = vec![vec![]; rails];
+    let mut rail = 0;
+    let mut direction = 1;
+
+    for i in 0..n {
+        fence[rail].push(i);
+        if rail == 0 {
+            direction = 1;
+        } else if rail == rails - 1 {
+            direction = -1;
+        }
+        rail = (rail as i32 + direction) as usize;
+    }
+
+    let mut result = vec!['?'; n];
+    let mut cipher_idx = 0;
+
+    for rail_chars in fence.iter() {
+        for &pos in rail_chars {
+            if cipher_idx < cipher_chars.len() {
+                result[pos] = cipher_chars[cipher_idx];
+                cipher_idx += 1;
+            }
+        }
+    }
+
+    result.iter().collect()
+}
+
+/// Inverts `a·x + b mod 26`; `a` must be coprime with 26.
+pub fn decrypt_affine(text: &str, a: u8, b: u8) -> CipherResult<String> {
+    let inverse = mod_inverse(a as u32, 26).ok_or(CipherError::NotInvertible { a: a as usize, modulus: 26 })?;
+    let b = b % 26;
+    Ok(text.chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                let x = (c as u8 - b'a') as u32;
+                let y = ((inverse * (x + b as u32)) % 26) as u8;
+                (b'a' + y) as char
+            } else if c.is_ascii_uppercase() {
+                let x = (c as u8 - b'A') as u32;
+                let y = ((inverse * (x + b as u32)) % 26) as u8;
+                (b'A' + y) as char
+            } else {
+                c
+            }
+        })
+        .collect())
+}
+
+// Extended Euclid; None when `a` and `m` are not coprime (no inverse).
+fn mod_inverse(a: u32, m: u32) -> Option<u32> {
+    if m == 0 {
+        return None;
+    }
+    let (mut r0, mut r1) = (m as i64, (a % m) as i64);
+    let (mut t0, mut t1) = (0i64, 1i64);
+
+    while r1 != 0 {
+        let q = r0 / r1;
+        (r0, r1) = (r1, r0 - q * r1);
+        (t0, t1) = (t1, t0 - q * t1);
+    }
+
+    if r0 != 1 {
+        return None;
+    }
+    Some(t0.rem_euclid(m as i64) as u32)
+}
+
+/// Beaufort with a repeating key of shifts: each letter becomes the key
+/// letter minus the ciphertext letter.
+pub fn decrypt_beaufort(text: &str, key: &[u8]) -> CipherResult<String> {
+    check_shift_key(key, 26)?;
+    let mut out = String::with_capacity(text.len());
+    let mut k = 0;
+
+    for c in text.chars() {
+        if c.is_ascii_alphabetic() {
+            let shift = key[k % key.len()];
+            let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+            let d = ((shift + 26 - (c as u8 - base)) % 26) + base;
+            out.push(d as char);
+            k += 1;
+        } else {
+            out.push(c);
+        }
+    }
+
+    Ok(out)
+}
+
+/// Undoes a columnar transposition whose columns were read off in the
+/// alphabetical order of `key`.
+pub fn decrypt_columnar_transposition(text: &str, key: &str) -> CipherResult<String> {
+    let key: Vec<char> = key.chars().collect();
+    if key.is_empty() {
+        return Err(CipherError::EmptyKey);
+    }
+    let cols = key.len();
+    let chars: Vec<char> = text.chars().collect();
+    let rows = chars.len().div_ceil(cols);
+
+    let mut key_indices: Vec<usize> = (0..cols).collect();
+    key_indices.sort_by_key(|&i| key[i]);
+
+    let mut result = vec!['?'; chars.len()];
+    let mut read_idx = 0;
+
+    for original_pos in key_indices.iter() {
+        for row in 0..rows {
+            let pos = row * cols + original_pos;
+            if pos < chars.len() && read_idx < chars.len() {
+                result[pos] = chars[read_idx];
+                read_idx += 1;
+            }
+        }
+    }
+
+    Ok(result.iter().collect())
+}
+
+/// The inverse of [`decrypt_columnar_transposition`].
+pub fn encrypt_columnar_transposition(text: &str, key: &str) -> CipherResult<String> {
+    let key: Vec<char> = key.chars().collect();
+    if key.is_empty() {
+        return Err(CipherError::EmptyKey);
+    }
+    let cols = key.len();
+    let chars: Vec<char> = text.chars().collect();
+
+    let mut key_indices: Vec<usize> = (0..cols).collect();
+    key_indices.sort_by_key(|&i| key[i]);
+
+    let mut result = String::with_capacity(text.len());
+    for original_pos in key_indices {
+        result.extend(chars.iter().skip(original_pos).step_by(cols));
+    }
+    Ok(result)
+}
+
+/// Decrypts with the 5×5 square keyed by `key` (I and J share a cell),
+/// then removes the x padding.
+pub fn decrypt_playfair(text: &str, key: &str) -> CipherResult<String> {
+    let key_lower = key.to_lowercase().replace('j', "i");
+    let mut keytable = String::new();
+    let mut seen = std::collections::HashSet::new();
+    
+    for c in key_lower.chars() {
+        if c.is_ascii_alphabetic() && !seen.contains(&c) {
+            keytable.push(c);
+            seen.insert(c);
+        }
+    }
+    
+    for c in 'a'..='z' {
+        if c != 'j' && !seen.contains(&c) {
+            keytable.push(c);
+        }
+    }
+
+    let mut result = String::new();
+    let clean_text: Vec<char> = text.chars()
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| if c.eq_ignore_ascii_case(&'j') { 'i' } else { c.to_ascii_lowercase() })
+        .collect();
+
+    if !clean_text.len().is_multiple_of(2) {
+        return Err(CipherError::InvalidInput(format!("Playfair ciphertext has an odd number of letters ({})", clean_text.len())));
+    }
+    let find = |c: char| keytable.find(c).ok_or_else(|| CipherError::InvalidInput(format!("'{}' is not in the Playfair square", c)));
+
+    for pair in clean_text.chunks_exact(2) {
+        let pos1 = find(pair[0])?;
+        let pos2 = find(pair[1])?;
+
+        let row1 = pos1 / 5;
+        let col1 = pos1 % 5;
+        let row2 = pos2 / 5;
+        let col2 = pos2 % 5;
+
+        if row1 == row2 {
+            let new_col1 = (col1 + 4) % 5;
+            let new_col2 = (col2 + 4) % 5;
+            result.push(keytable.chars().nth(row1 * 5 + new_col1).unwrap());
+            result.push(keytable.chars().nth(row2 * 5 + new_col2).unwrap());
+        } else if col1 == col2 {
+            let new_row1 = (row1 + 4) % 5;
+            let new_row2 = (row2 + 4) % 5;
+            result.push(keytable.chars().nth(new_row1 * 5 + col1).unwrap());
+            result.push(keytable.chars().nth(new_row2 * 5 + col2).unwrap());
+        } else {
+            result.push(keytable.chars().nth(row1 * 5 + col2).unwrap());
+            result.push(keytable.chars().nth(row2 * 5 + col1).unwrap());
+        }
+    }
+
+    Ok(strip_playfair_padding(&result))
+}
+
+// Encryption splits doubled letters in a digraph with an x ("balloon" ->
+// "ba lx lo on") and pads odd-length text with a trailing x; undo both.
+fn strip_playfair_padding(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut result = String::with_capacity(chars.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let filler = c == 'x' && i % 2 == 1;
+        let between_doubles = filler && i + 1 < chars.len() && chars[i - 1] == chars[i + 1];
+        let trailing = filler && i + 1 == chars.len();
+        if !between_doubles && !trailing {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Reads row/column digit pairs (1-5) from the a-z square without j.
+pub fn decrypt_polybius_square(text: &str) -> CipherResult<String> {
+    let polybius: Vec<char> = "abcdefghiklmnopqrstuvwxyz".chars().collect();
+    let mut result = String::new();
+    let clean_text: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if let Some(bad) = clean_text.iter().find(|c| !('1'..='5').contains(*c)) {
+        return Err(CipherError::InvalidInput(format!("'{}' is not a Polybius coordinate (1-5)", bad)));
+    }
+    if !clean_text.len().is_multiple_of(2) {
+        return Err(CipherError::InvalidInput(format!("Polybius ciphertext has an odd number of digits ({})", clean_text.len())));
+    }
+
+    for pair in clean_text.chunks_exact(2) {
+        let row = pair[0] as usize - '1' as usize;
+        let col = pair[1] as usize - '1' as usize;
+        result.push(polybius[row * 5 + col]);
+    }
+
+    Ok(result)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BaconTable {
+    Full26,
+    Classic24,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum BaconEncoding {
+    Letters,
+    Binary,
+    Case,
+    Typeface,
+}
+
+/// One way of reading a Bacon cipher: the 24- or 26-letter table, how the
+/// two symbols are written, and which of them is 1.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct BaconVariant {
+    table: BaconTable,
+    encoding: BaconEncoding,
+    swapped: bool,
+}
+
+impl BaconVariant {
+    /// E.g. `26-letter, a/b (swapped)`.
+    pub fn describe(&self) -> String {
+        let table = match self.table {
+            BaconTable::Full26 => "26-letter",
+            BaconTable::Classic24 => "24-letter",
+        };
+        let encoding = match self.encoding {
+            BaconEncoding::Letters => "a/b",
+            BaconEncoding::Binary => "0/1",
+            BaconEncoding::Case => "lower/upper case",
+            BaconEncoding::Typeface => "plain/styled typeface",
+        };
+        if self.swapped {
+            format!("{}, {} (swapped)", table, encoding)
+        } else {
+            format!("{}, {}", table, encoding)
+        }
+    }
+}
+
+/// Works out which bit encodings are plausible for the text, then pairs each
+/// with both tables and both polarities.
+pub fn detect_bacon_variants(text: &str) -> Vec<BaconVariant> {
+    let letters: Vec<char> = text.chars().filter(|c| c.is_alphabetic()).collect();
+    let symbols: Vec<char> = text.chars().filter(|c| c.is_alphanumeric()).collect();
+
+    let mut encodings = Vec::new();
+
+    if !letters.is_empty() && letters.iter().all(|c| matches!(c.to_ascii_lowercase(), 'a' | 'b')) {
+        encodings.push(BaconEncoding::Letters);
+    }
+    if !symbols.is_empty() && symbols.iter().all(|&c| c == '0' || c == '1') {
+        encodings.push(BaconEncoding::Binary);
+    }
+    let has_lower = letters.iter().any(|c| c.is_ascii_lowercase());
+    let has_upper = letters.iter().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        encodings.push(BaconEncoding::Case);
+    }
+    let has_plain = letters.iter().any(|c| c.is_ascii());
+    let has_styled = letters.iter().any(|c| !c.is_ascii());
+    if has_plain && has_styled {
+        encodings.push(BaconEncoding::Typeface);
+    }
+
+    let mut variants = Vec::new();
+    for &encoding in &encodings {
+        for table in [BaconTable::Full26, BaconTable::Classic24] {
+            for swapped in [false, true] {
+                variants.push(BaconVariant { table, encoding, swapped });
+            }
+        }
+    }
+    variants
+}
+
+fn bacon_bits(text: &str, encoding: BaconEncoding) -> Vec<bool> {
+    match encoding {
+        BaconEncoding::Letters => text
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .map(|c| c.eq_ignore_ascii_case(&'b'))
+            .collect(),
+        BaconEncoding::Binary => text
+            .chars()
+            .filter(|&c| c == '0' || c == '1')
+            .map(|c| c == '1')
+            .collect(),
+        BaconEncoding::Case => text
+            .chars()
+            .filter(|c| c.is_ascii_alphabetic())
+            .map(|c| c.is_ascii_uppercase())
+            .collect(),
+        BaconEncoding::Typeface => text
+            .chars()
+            .filter(|c| c.is_alphabetic())
+            .map(|c| !c.is_ascii())
+            .collect(),
+    }
+}
+
+/// Reads five-symbol groups as letters; a partial group at the end is
+/// dropped.
+pub fn decrypt_bacon(text: &str, variant: BaconVariant) -> String {
+    let alphabet = match variant.table {
+        BaconTable::Full26 => "abcdefghijklmnopqrstuvwxyz",
+        BaconTable::Classic24 => "abcdefghiklmnopqrstuwxyz",
+    };
+
+    let bits = bacon_bits(text, variant.encoding);
+    let mut result = String::new();
+
+    for chunk in bits.chunks_exact(5) {
+        let idx = chunk
+            .iter()
+            .fold(0usize, |acc, &bit| (acc << 1) | (bit != variant.swapped) as usize);
+        if let Some(letter) = alphabet.chars().nth(idx) {
+            result.push(letter);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod bacon_tests {
+    use super::*;
+
+    // Writes `plain` in the given variant: a/b or 0/1 in groups of five, or a
+    // cover text whose case or typeface carries the bits.
+    fn encrypt_bacon(plain: &str, variant: BaconVariant) -> String {
+        let alphabet = match variant.table {
+            BaconTable::Full26 => "abcdefghijklmnopqrstuvwxyz",
+            BaconTable::Classic24 => "abcdefghiklmnopqrstuwxyz",
+        };
+        let bits: Vec<bool> = plain
+            .chars()
+            .flat_map(|c| {
+                let idx = alphabet.find(c).unwrap();
+                (0..5).rev().map(move |bit| (idx >> bit & 1 == 1) != variant.swapped)
+            })
+            .collect();
+        let cover = "francisbaconwroteofbiliteralciphers".chars().cycle();
+        let symbols: Vec<char> = match variant.encoding {
+            BaconEncoding::Letters => bits.iter().map(|&b| if b { 'b' } else { 'a' }).collect(),
+            BaconEncoding::Binary => bits.iter().map(|&b| if b { '1' } else { '0' }).collect(),
+            BaconEncoding::Case => bits.iter().zip(cover).map(|(&b, c)| if b { c.to_ascii_uppercase() } else { c }).collect(),
+            // Full-width letters stand in for the second typeface.
+            BaconEncoding::Typeface => bits
+                .iter()
+                .zip(cover)
+                .map(|(&b, c)| if b { char::from_u32(c as u32 - 'a' as u32 + 0xFF41).unwrap() } else { c })
+                .collect(),
+        };
+        symbols.chunks(5).map(|g| g.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn every_variant_round_trips() {
+        // No j or v, which the 24-letter table shares with i and u.
+        let plain = "thequickbrownfox";
+        for table in [BaconTable::Full26, BaconTable::Classic24] {
+            for encoding in [BaconEncoding::Letters, BaconEncoding::Binary, BaconEncoding::Case, BaconEncoding::Typeface] {
+                for swapped in [false, true] {
+                    let variant = BaconVariant { table, encoding, swapped };
+                    let ciphertext = encrypt_bacon(plain, variant);
+                    assert!(detect_bacon_variants(&ciphertext).contains(&variant), "{} not detected", variant.describe());
+                    assert_eq!(decrypt_bacon(&ciphertext, variant), plain, "{}", variant.describe());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tables_differ_after_i() {
+        let variant = |table| BaconVariant { table, encoding: BaconEncoding::Letters, swapped: false };
+        // Index 9 is j in the 26-letter table and k in the 24-letter one.
+        assert_eq!(decrypt_bacon("abaab", variant(BaconTable::Full26)), "j");
+        assert_eq!(decrypt_bacon("abaab", variant(BaconTable::Classic24)), "k");
+        assert_eq!(decrypt_bacon("aaaab aaa", variant(BaconTable::Full26)), "b");
+    }
+}
+
+/// Reverses the text.
+pub fn decrypt_reverse(text: &str) -> String {
+    text.chars().rev().collect()
+}
+
+// One letter of the Atbash + Vigenère hybrid: Atbash, then subtract the key.
+fn atbash_vigenere_letter(c: u8, k: u8) -> u8 {
+    (25 - c + 26 - k) % 26
+}
+
+/// Atbash followed by Vigenère with a repeating key of shifts (0-25), in
+/// one pass. Case is kept and the key only advances on letters.
+pub fn decrypt_atbash_vigenere(text: &str, key: &[u8]) -> CipherResult<String> {
+    check_shift_key(key, 26)?;
+    let mut buffer = LetterBuffer::new(text);
+    let plain = buffer.apply(key, atbash_vigenere_letter);
+    Ok(std::str::from_utf8(plain).unwrap().to_string())
+}
+
+#[cfg(test)]
+mod hybrid_tests {
+    use super::*;
+
+    #[test]
+    fn atbash_vigenere_matches_atbash_then_vigenere() {
+        let texts = ["bxrworn, dodcx iy lbks !", "Hello, World", "naïve café — 123", ""];
+        let keys: [&[u8]; 4] = [&[0], &[3], &[10, 4, 24], &[25, 0, 13, 7, 1]];
+        for text in texts {
+            for key in keys {
+                let original = decrypt_vigenere(&decrypt_atbash(text), key).unwrap();
+                assert_eq!(decrypt_atbash_vigenere(text, key).unwrap(), original, "{:?} with {:?}", text, key);
+            }
+        }
+        assert!(decrypt_atbash_vigenere("abc", &[]).is_err());
+        assert!(decrypt_atbash_vigenere("abc", &[26]).is_err());
+    }
+}
+
+// ========== ALPHABETS ==========
+
+// Largest brute-force space for shift-family keys over a custom alphabet,
+// matching the 26^5 Vigenère search over a-z.
+const MAX_ALPHABET_KEYSPACE: usize = 11_881_376;
+
+/// Symbol set for the shift-family ciphers. With `fold_case` only lowercase
+/// symbols are listed and uppercase input maps onto them, keeping its case on
+/// output (the classic a-z behaviour). Characters outside the set pass
+/// through unchanged.
+#[derive(Clone)]
+pub struct Alphabet {
+    name: String,
+    symbols: Vec<char>,
+    fold_case: bool,
+}
+
+impl Alphabet {
+    /// The lowercase a-z alphabet, folding uppercase onto it.
+    pub fn letters() -> Self {
+        Alphabet { name: "a-z".to_string(), symbols: ('a'..='z').collect(), fold_case: true }
+    }
+
+    /// `az`, `az09`, `base64`, `printable`, or any other string taken as the
+    /// symbols themselves, in order.
+    pub fn parse(spec: &str) -> CipherResult<Self> {
+        let (name, symbols, fold_case): (&str, Vec<char>, bool) = match spec {
+            "az" | "a-z" | "letters" => return Ok(Self::letters()),
+            "az09" | "a-z0-9" | "alnum" => ("a-z0-9", ('a'..='z').chain('0'..='9').collect(), true),
+            "base64" => ("base64", ('A'..='Z').chain('a'..='z').chain('0'..='9').chain(['+', '/']).collect(), false),
+            "printable" | "ascii" => ("printable", (' '..='~').collect(), false),
+            custom => ("custom", custom.chars().collect(), false),
+        };
+
+        if symbols.len() < 2 {
+            return Err(CipherError::InvalidKey("an alphabet needs at least two symbols".to_string()));
+        }
+        for (i, c) in symbols.iter().enumerate() {
+            if symbols[..i].contains(c) {
+                return Err(CipherError::InvalidKey(format!("symbol '{}' appears twice in the alphabet", c)));
+            }
+        }
+        Ok(Alphabet { name: name.to_string(), symbols, fold_case })
+    }
+
+    /// Short name such as `a-z` or `base64`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Number of symbols (at least two).
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Never true for an alphabet from [`Alphabet::letters`] or [`Alphabet::parse`].
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    // Index of `c` plus whether it was folded from uppercase.
+    fn position(&self, c: char) -> Option<(usize, bool)> {
+        if let Some(i) = self.symbols.iter().position(|&s| s == c) {
+            return Some((i, false));
+        }
+        if self.fold_case && c.is_ascii_uppercase() {
+            let lower = c.to_ascii_lowercase();
+            return self.symbols.iter().position(|&s| s == lower).map(|i| (i, true));
+        }
+        None
+    }
+
+    fn symbol(&self, index: usize, upper: bool) -> char {
+        let c = self.symbols[index % self.len()];
+        if upper { c.to_ascii_uppercase() } else { c }
+    }
+
+    // Rewrites every alphabet symbol through `step`, which also receives the
+    // running count of symbols seen so far (the key position).
+    fn map<F: FnMut(usize, usize) -> usize>(&self, text: &str, mut step: F) -> String {
+        let mut k = 0;
+        text.chars()
+            .map(|c| match self.position(c) {
+                Some((i, upper)) => {
+                    let out = self.symbol(step(i, k), upper);
+                    k += 1;
+                    out
+                }
+                None => c,
+            })
+            .collect()
+    }
+
+    /// A key's symbols as indices into the alphabet.
+    pub fn key_values(&self, key: &str) -> CipherResult<Vec<usize>> {
+        if key.is_empty() {
+            return Err(CipherError::EmptyKey);
+        }
+        key.chars()
+            .map(|c| {
+                self.position(c)
+                    .map(|(i, _)| i)
+                    .ok_or_else(|| CipherError::InvalidKey(format!("'{}' is not in the {} alphabet", c, self.name)))
+            })
+            .collect()
+    }
+
+    /// The key written back out as symbols.
+    pub fn key_label(&self, key: &[usize]) -> String {
+        key.iter().map(|&k| self.symbol(k, false)).collect()
+    }
+
+    // Longest key length whose full keyspace stays within the brute-force
+    // budget.
+    fn max_key_len(&self, limit: usize) -> usize {
+        let mut len = 0;
+        let mut space = 1usize;
+        while len < limit && space.saturating_mul(self.len()) <= MAX_ALPHABET_KEYSPACE {
+            space *= self.len();
+            len += 1;
+        }
+        len
+    }
+}
+
+/// [`decrypt_caesar`] over any alphabet.
+pub fn decrypt_caesar_in(text: &str, shift: usize, alphabet: &Alphabet) -> String {
+    let n = alphabet.len();
+    alphabet.map(text, |i, _| (i + n - shift % n) % n)
+}
+
+/// [`decrypt_vigenere`] over any alphabet.
+pub fn decrypt_vigenere_in(text: &str, key: &[usize], alphabet: &Alphabet) -> CipherResult<String> {
+    let n = alphabet.len();
+    check_shift_key(key, n)?;
+    Ok(alphabet.map(text, |i, k| (i + n - key[k % key.len()]) % n))
+}
+
+/// [`decrypt_beaufort`] over any alphabet.
+pub fn decrypt_beaufort_in(text: &str, key: &[usize], alphabet: &Alphabet) -> CipherResult<String> {
+    let n = alphabet.len();
+    check_shift_key(key, n)?;
+    Ok(alphabet.map(text, |i, k| (key[k % key.len()] + n - i) % n))
+}
+
+/// [`decrypt_affine`] over any alphabet.
+pub fn decrypt_affine_in(text: &str, a: usize, b: usize, alphabet: &Alphabet) -> CipherResult<String> {
+    let n = alphabet.len();
+    let inverse = mod_inverse(a as u32, n as u32).ok_or(CipherError::NotInvertible { a, modulus: n })? as usize;
+    Ok(alphabet.map(text, |i, _| inverse * ((i + b) % n) % n))
+}
+
+/// Brute force for the shift family over any alphabet: every Caesar shift,
+/// every invertible affine pair, and Vigenère/Beaufort keys up to the length
+/// the keyspace budget allows.
+pub fn crack_shift_ciphers_in(session: &Session, ciphertext: &str, alphabet: &Alphabet, top_n: &mut TopN) {
+    let n = alphabet.len();
+    let tag = |cipher: &str| format!("{} [{}]", cipher, alphabet.name);
+
+    for shift in 0..n {
+        let plain = decrypt_caesar_in(ciphertext, shift, alphabet);
+        let score = session.scorer.score(&plain);
+        top_n.insert_lightweight(score, tag("Caesar"), format!("shift {}", shift), &plain);
+    }
+
+    for a in 1..n {
+        for b in 0..n {
+            let Ok(plain) = decrypt_affine_in(ciphertext, a, b, alphabet) else {
+                break;
+            };
+            let score = session.scorer.score(&plain);
+            top_n.insert_lightweight(score, tag("Affine"), format!("a={}, b={}", a, b), &plain);
+        }
+    }
+
+    let max_len = alphabet.max_key_len(5);
+    let mut progress = Progress::new((1..=max_len as u32).map(|len| n.pow(len) as u64).sum());
+    for len in 1..=max_len {
+        let total = n.pow(len as u32);
+        let mut key = vec![0usize; len];
+        session.status(format!("  Trying {}-symbol keys over {}...", len, alphabet.name));
+
+        for i in 0..total {
+            if session.should_stop() {
+                break;
+            }
+            let mut rest = i;
+            for j in (0..len).rev() {
+                key[j] = rest % n;
+                rest /= n;
+            }
+
+            let label = format!("key: {}", alphabet.key_label(&key));
+            if let Ok(plain) = decrypt_vigenere_in(ciphertext, &key, alphabet) {
+                let score = session.scorer.score(&plain);
+                top_n.insert_lightweight(score, tag("Vigenère"), label.clone(), &plain);
+            }
+            if let Ok(plain) = decrypt_beaufort_in(ciphertext, &key, alphabet) {
+                let score = session.scorer.score(&plain);
+                top_n.insert_lightweight(score, tag("Beaufort"), label, &plain);
+            }
+            progress.tick(session, top_n);
+        }
+    }
+    progress.finish(session);
+}
+
+// ========== KEYWORD SUBSTITUTION ==========
+
+/// Dedupes the keyword's letters, then appends the rest of the alphabet,
+/// either from `a` or continuing after the keyword's last letter (wrapping).
+pub fn keyword_alphabet(keyword: &str, from_last: bool) -> Vec<char> {
+    let mut alphabet: Vec<char> = Vec::with_capacity(26);
+    let keyword: Vec<char> = keyword.chars().map(|c| c.to_ascii_lowercase()).filter(char::is_ascii_lowercase).collect();
+    let start = match keyword.last() {
+        Some(&last) if from_last => (last as u8 - b'a' + 1) % 26,
+        _ => 0,
+    };
+    let rest = (0..26).map(|i| (b'a' + (start + i) % 26) as char);
+    for c in keyword.iter().copied().chain(rest) {
+        if !alphabet.contains(&c) {
+            alphabet.push(c);
+        }
+    }
+    alphabet
+}
+
+/// Plain alphabet a-z enciphers to the mixed alphabet rotated by `shift`.
+/// The mixed alphabet must be a permutation of the 26 lowercase letters.
+pub fn decrypt_keyword(text: &str, alphabet: &[char], shift: u8) -> CipherResult<String> {
+    if alphabet.len() != 26 {
+        return Err(CipherError::InvalidKey(format!("a mixed alphabet needs 26 letters, not {}", alphabet.len())));
+    }
+    let mut inverse = [None; 26];
+    for (i, &c) in alphabet.iter().enumerate() {
+        if !c.is_ascii_lowercase() {
+            return Err(CipherError::InvalidKey(format!("'{}' is not a lowercase letter", c)));
+        }
+        let slot = &mut inverse[(c as u8 - b'a') as usize];
+        if slot.is_some() {
+            return Err(CipherError::InvalidKey(format!("'{}' appears twice in the mixed alphabet", c)));
+        }
+        *slot = Some(((i + 26 - shift as usize % 26) % 26) as u8);
+    }
+    Ok(map_letters(text, |c| inverse[c as usize].unwrap_or(c)))
+}
+
+/// Wordlist attack: every word as keyword, both fill orders, all 26 shifts.
+/// Words that build the same mixed alphabet are only tried once.
+pub fn crack_keyword_substitution(session: &Session, ciphertext: &str, words: &[&str], top_n: &mut TopN) {
+    let mut seen: std::collections::HashSet<Vec<char>> = std::collections::HashSet::new();
+    for &word in words {
+        if session.should_stop() {
+            break;
+        }
+        for from_last in [false, true] {
+            let alphabet = keyword_alphabet(word, from_last);
+            if !seen.insert(alphabet.clone()) {
+                continue;
+            }
+            let fill = if from_last { ", from last letter" } else { "" };
+            for shift in 0..26 {
+                let Ok(plain) = decrypt_keyword(ciphertext, &alphabet, shift) else {
+                    continue;
+                };
+                let score = session.scorer.score(&plain);
+                let params = format!("keyword: {}, shift {}{}", word.to_lowercase(), shift, fill);
+                top_n.insert_lightweight(score, "Keyword".to_string(), params, &plain);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod keyword_tests {
+    use super::*;
+
+    #[test]
+    fn rejects_alphabets_that_are_not_permutations() {
+        let mut alphabet = keyword_alphabet("kryptos", false);
+        assert!(decrypt_keyword("abc", &alphabet, 0).is_ok());
+        assert!(decrypt_keyword("abc", &alphabet[..25], 0).is_err());
+        alphabet[3] = 'K';
+        assert!(decrypt_keyword("abc", &alphabet, 0).is_err());
+        alphabet[3] = 'é';
+        assert!(decrypt_keyword("abc", &alphabet, 0).is_err());
+        alphabet[3] = alphabet[4];
+        assert!(decrypt_keyword("abc", &alphabet, 0).is_err());
+    }
+
+    #[test]
+    fn any_shift_is_accepted() {
+        let alphabet = keyword_alphabet("kryptos", true);
+        assert_eq!(decrypt_keyword("Hello", &alphabet, 255), decrypt_keyword("Hello", &alphabet, 255 % 26));
+    }
+}
+
+// ========== BYTE CIPHERS ==========
+
+const MAX_XOR_KEYSIZE: usize = 40;
+const XOR_KEYSIZE_CANDIDATES: usize = 3;
+
+/// XORs every byte with `key`.
+pub fn xor_single(bytes: &[u8], key: u8) -> Vec<u8> {
+    bytes.iter().map(|&b| b ^ key).collect()
+}
+
+/// XORs the bytes with `key` repeated end to end.
+pub fn xor_repeating(bytes: &[u8], key: &[u8]) -> Vec<u8> {
+    bytes.iter().zip(key.iter().cycle()).map(|(&b, &k)| b ^ k).collect()
+}
+
+fn hamming_distance(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+// Renders bytes as text, escaping anything that isn't printable ASCII so
+// binary candidates can still go through TopN and the result display.
+fn escape_bytes(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    for &b in bytes {
+        if b.is_ascii_graphic() || b == b' ' || b == b'\n' {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("\\x{:02x}", b));
+        }
+    }
+    out
+}
+
+// Cheap per-byte fitness used to pick each key byte: English letters and
+// spaces score, unprintable bytes are heavily penalised.
+fn score_bytes(bytes: &[u8]) -> i32 {
+    let mut score = 0;
+    for &b in bytes {
+        let lower = b.to_ascii_lowercase();
+        if b == b' ' || FREQ.as_bytes().contains(&lower) {
+            score += 2;
+        } else if b.is_ascii_alphabetic() {
+            score += 1;
+        } else if !(b.is_ascii_graphic() || b.is_ascii_whitespace()) {
+            score -= 10;
+        }
+    }
+    score
+}
+
+fn score_plain_bytes(scorer: &Scorer, bytes: &[u8]) -> i32 {
+    score_bytes(bytes) + scorer.score(&String::from_utf8_lossy(bytes))
+}
+
+// Normalised Hamming distance between consecutive keysize blocks; the true
+// keysize tends to give the smallest distance. On a stop, the sizes measured
+// so far are ranked.
+fn estimate_xor_keysizes(session: &Session, bytes: &[u8]) -> Vec<usize> {
+    let max = MAX_XOR_KEYSIZE.min(bytes.len() / 2);
+    let mut distances: Vec<(f64, usize)> = Vec::new();
+
+    for keysize in 1..=max {
+        if session.should_stop() {
+            break;
+        }
+        let blocks: Vec<&[u8]> = bytes.chunks_exact(keysize).collect();
+        if blocks.len() < 2 {
+            continue;
+        }
+        let pairs = blocks.len() - 1;
+        let total: u32 = blocks.windows(2).map(|w| hamming_distance(w[0], w[1])).sum();
+        distances.push((total as f64 / pairs as f64 / keysize as f64, keysize));
+    }
+
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+    distances.into_iter().take(XOR_KEYSIZE_CANDIDATES).map(|(_, k)| k).collect()
+}
+
+fn best_single_byte_key(bytes: &[u8]) -> u8 {
+    (0..=255u8)
+        .max_by_key(|&k| score_bytes(&xor_single(bytes, k)))
+        .unwrap_or(0)
+}
+
+/// Tries all 256 single-byte keys.
+pub fn crack_single_byte_xor(session: &Session, bytes: &[u8], top_n: &mut TopN) {
+    for key in 0..=255u8 {
+        if session.should_stop() {
+            break;
+        }
+        let plain = xor_single(bytes, key);
+        let score = score_plain_bytes(session.scorer, &plain);
+        top_n.insert_lightweight(score, "XOR".to_string(), format!("key: 0x{:02x}", key), &escape_bytes(&plain));
+    }
+}
+
+/// Guesses likely key sizes from Hamming distances, then solves each key
+/// byte as a single-byte XOR.
+pub fn crack_repeating_xor(session: &Session, bytes: &[u8], top_n: &mut TopN) {
+    for keysize in estimate_xor_keysizes(session, bytes) {
+        if session.should_stop() {
+            break;
+        }
+        let mut key: Vec<u8> = (0..keysize)
+            .map(|offset| {
+                let column: Vec<u8> = bytes.iter().skip(offset).step_by(keysize).copied().collect();
+                best_single_byte_key(&column)
+            })
+            .collect();
+        // A multiple of the true size recovers the key repeated; report one copy.
+        key.truncate(key_period(&key));
+
+        let plain = xor_repeating(bytes, &key);
+        let score = score_plain_bytes(session.scorer, &plain);
+        top_n.insert_lightweight(
+            score,
+            "Repeating XOR".to_string(),
+            format!("key: \"{}\" ({} bytes)", escape_bytes(&key), key.len()),
+            &escape_bytes(&plain),
+        );
+    }
+}
+
+/// Ciphertext given as hex or base64 is decoded to bytes; anything else is
+/// taken as raw bytes.
+pub fn ciphertext_bytes(text: &str) -> Vec<u8> {
+    hex_to_bytes(text)
+        .or_else(|| base64_to_bytes(text))
+        .unwrap_or_else(|| text.as_bytes().to_vec())
+}
+
+#[cfg(test)]
+mod xor_tests {
+    use super::*;
+
+    const PLAIN: &str = "The ancient library stood majestically on the hillside, its weathered stone walls \
+        holding countless secrets within. Scholars would journey across the kingdom for months \
+        to access its rare manuscripts, and the head librarian guarded those treasures fiercely, \
+        allowing only dedicated researchers to study them carefully.";
+
+    #[test]
+    fn hamming_distance_counts_differing_bits() {
+        assert_eq!(hamming_distance(b"this is a test", b"wokka wokka!!!"), 37);
+        assert_eq!(hamming_distance(b"same", b"same"), 0);
+    }
+
+    #[test]
+    fn recovers_a_single_byte_key() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let mut top_n = TopN::new(5);
+        crack_single_byte_xor(&Session::new(&scorer, &config), &xor_single(PLAIN.as_bytes(), 0x5a), &mut top_n);
+        let best = top_n.best_result().unwrap();
+        assert_eq!(best.params, "key: 0x5a");
+        assert_eq!(best.plaintext_full, PLAIN);
+    }
+
+    #[test]
+    fn recovers_a_repeating_key_and_plaintext() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let session = Session::new(&scorer, &config);
+        let ciphertext = xor_repeating(PLAIN.as_bytes(), b"Kryptos");
+        assert!(estimate_xor_keysizes(&session, &ciphertext).iter().all(|size| size % 7 == 0));
+
+        let mut top_n = TopN::new(5);
+        crack_repeating_xor(&session, &ciphertext, &mut top_n);
+        let best = top_n.best_result().unwrap();
+        assert_eq!(best.params, "key: \"Kryptos\" (7 bytes)");
+        assert_eq!(best.plaintext_full, PLAIN);
+    }
+
+    #[test]
+    fn stop_flag_ends_the_xor_attacks() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop);
+        let ciphertext = xor_repeating(PLAIN.as_bytes(), b"Kryptos");
+        assert!(estimate_xor_keysizes(&session, &ciphertext).is_empty());
+
+        let mut top_n = TopN::new(5);
+        crack_single_byte_xor(&session, &ciphertext, &mut top_n);
+        crack_repeating_xor(&session, &ciphertext, &mut top_n);
+        assert!(top_n.best_result().is_none());
+    }
+}
+
+// ========== RUNNING KEY & BOOK CIPHERS ==========
+
+/// The ASCII letters of `text` as values 0-25; everything else is dropped.
+pub fn letter_values(text: &str) -> Vec<u8> {
+    text.bytes()
+        .filter(|b| b.is_ascii_alphabetic())
+        .map(|b| b.to_ascii_lowercase() - b'a')
+        .collect()
+}
+
+/// Slides the running key over every offset of a reference text, i.e.
+/// Vigenère with a key as long as the message.
+pub fn crack_running_key(session: &Session, ciphertext: &str, corpus: &str, top_n: &mut TopN) {
+    let needed = letter_values(ciphertext).len();
+    let key_stream = letter_values(corpus);
+    if needed == 0 || key_stream.len() < needed {
+        return;
+    }
+
+    let offsets = key_stream.len() - needed + 1;
+    let mut progress = Progress::new(offsets as u64);
+    for offset in 0..offsets {
+        if session.should_stop() {
+            break;
+        }
+        let key = &key_stream[offset..offset + needed];
+        if let Ok(plain) = decrypt_vigenere(ciphertext, key) {
+            let score = session.scorer.score(&plain);
+            let preview: String = key_label(&key[..needed.min(20)]);
+            top_n.insert_lightweight(score, "Running Key".to_string(), format!("offset {} (key: {}...)", offset, preview), &plain);
+        }
+        progress.tick(session, top_n);
+    }
+    progress.finish(session);
+}
+
+// Splits ciphertext into plaintext + key, both assumed English, with a
+// Viterbi pass over the bigram model. The state is the previous plaintext
+// letter; the previous key letter follows from it and the ciphertext.
+// Gives up with `None` on a stop.
+fn split_running_key(session: &Session, ciphertext: &str) -> Option<(String, String)> {
+    let cipher = letter_values(ciphertext);
+    if cipher.is_empty() {
+        return None;
+    }
+
+    let model = session.scorer.bigram_model();
+    let key_of = |c: u8, p: usize| ((c as usize + 26 - p) % 26) as u8 as usize;
+
+    let mut best = [0.0f64; 26];
+    for (p, score) in best.iter_mut().enumerate() {
+        *score = model.start[p] + model.start[key_of(cipher[0], p)];
+    }
+    let mut back: Vec<[u8; 26]> = Vec::with_capacity(cipher.len());
+
+    for i in 1..cipher.len() {
+        if session.should_stop() {
+            return None;
+        }
+        let mut scores = [f64::NEG_INFINITY; 26];
+        let mut from = [0u8; 26];
+        for (p, (score, prev_slot)) in scores.iter_mut().zip(from.iter_mut()).enumerate() {
+            let k = key_of(cipher[i], p);
+            for (prev, &prev_score) in best.iter().enumerate() {
+                let prev_k = key_of(cipher[i - 1], prev);
+                let s = prev_score + model.next[prev][p] + model.next[prev_k][k];
+                if s > *score {
+                    *score = s;
+                    *prev_slot = prev as u8;
+                }
+            }
+        }
+        back.push(from);
+        best = scores;
+    }
+
+    let mut p = (0..26).max_by(|&a, &b| best[a].total_cmp(&best[b]))?;
+    let mut plain_letters = vec![0u8; cipher.len()];
+    for i in (0..cipher.len()).rev() {
+        plain_letters[i] = p as u8;
+        if i > 0 {
+            p = back[i - 1][p] as usize;
+        }
+    }
+
+    // Put the recovered letters back into the ciphertext's layout.
+    let mut plain = String::with_capacity(ciphertext.len());
+    let mut key = String::with_capacity(ciphertext.len());
+    let mut idx = 0;
+    for c in ciphertext.chars() {
+        if c.is_ascii_alphabetic() {
+            let p = plain_letters[idx];
+            let k = key_of(cipher[idx], p as usize) as u8;
+            let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+            plain.push((base + p) as char);
+            key.push((base + k) as char);
+            idx += 1;
+        } else {
+            plain.push(c);
+            key.push(c);
+        }
+    }
+
+    Some((plain, key))
+}
+
+/// Running key without the source text: splits the ciphertext into two
+/// English-looking streams and ranks both as the plaintext.
+pub fn crack_running_key_statistical(session: &Session, ciphertext: &str, top_n: &mut TopN) {
+    if let Some((plain, key)) = split_running_key(session, ciphertext) {
+        // Plaintext and key are interchangeable, so rank both readings.
+        let score = session.scorer.score(&plain);
+        top_n.insert_lightweight(score, "Running Key".to_string(), format!("split, key: {}", key), &plain);
+        let score = session.scorer.score(&key);
+        top_n.insert_lightweight(score, "Running Key".to_string(), format!("split, key: {}", plain), &key);
+    }
+}
+
+/// Book cipher references are `page:line:word`, `line:word` or a bare word
+/// index, all 1-based. Pages are separated by form feeds; `.`, `-` and `/`
+/// are accepted in place of `:`.
+pub fn decrypt_book_cipher(references: &str, book: &str) -> Vec<Option<String>> {
+    let pages: Vec<Vec<&str>> = book.split('\x0c').map(|page| page.lines().collect()).collect();
+    let all_lines: Vec<&str> = book.lines().collect();
+    let all_words: Vec<&str> = book.split_whitespace().collect();
+
+    let clean_word = |w: &str| -> String {
+        w.trim_matches(|c: char| !c.is_alphanumeric()).to_string()
+    };
+    let word_in_line = |line: &str, n: usize| -> Option<String> {
+        line.split_whitespace().nth(n.checked_sub(1)?).map(clean_word)
+    };
+
+    references
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|r| !r.is_empty())
+        .map(|reference| {
+            let parts: Vec<usize> = reference
+                .split([':', '.', '-', '/'])
+                .map(|p| p.parse().ok())
+                .collect::<Option<Vec<usize>>>()?;
+            match parts.as_slice() {
+                [page, line, word] => {
+                    let page = pages.get(page.checked_sub(1)?)?;
+                    word_in_line(page.get(line.checked_sub(1)?)?, *word)
+                }
+                [line, word] => word_in_line(all_lines.get(line.checked_sub(1)?)?, *word),
+                [word] => all_words.get(word.checked_sub(1)?).map(|w| clean_word(w)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Decodes book cipher references, scoring the words found (missing
+/// references show as `?`).
+pub fn crack_book_cipher(session: &Session, references: &str, book: &str, top_n: &mut TopN) {
+    if session.should_stop() {
+        return;
+    }
+    let scorer = session.scorer;
+    let words = decrypt_book_cipher(references, book);
+    let missing = words.iter().filter(|w| w.is_none()).count();
+    let words: Vec<String> = words.into_iter().map(|w| w.unwrap_or_else(|| "?".to_string())).collect();
+
+    let plain = words.join(" ");
+    let score = scorer.score(&plain);
+    top_n.insert_lightweight(score, "Book Cipher".to_string(), format!("words ({} unresolved)", missing), &plain);
+
+    let initials: String = words.iter().filter_map(|w| w.chars().next()).collect();
+    let score = scorer.score(&initials);
+    top_n.insert_lightweight(score, "Book Cipher".to_string(), format!("first letters ({} unresolved)", missing), &initials);
+}
+
+#[cfg(test)]
+mod running_key_tests {
+    use super::*;
+
+    const BOOK: &str = "It was the best of times, it was the worst of times,\n\
+        it was the age of wisdom, it was the age of foolishness,\n\
+        it was the epoch of belief.\x0cIt was the season of Light,\n\
+        it was the season of Darkness.";
+
+    fn words(found: Vec<Option<String>>) -> Vec<String> {
+        found.into_iter().map(|w| w.unwrap_or_else(|| "?".to_string())).collect()
+    }
+
+    #[test]
+    fn book_references_pick_words() {
+        assert_eq!(words(decrypt_book_cipher("1:1:4 2:2:2 1-3-6", BOOK)), ["best", "was", "belief"]);
+        assert_eq!(words(decrypt_book_cipher("2:4, 1.1", BOOK)), ["age", "It"]);
+        assert_eq!(words(decrypt_book_cipher("6 42", BOOK)), ["times", "Darkness"]);
+    }
+
+    #[test]
+    fn out_of_range_references_are_none() {
+        let found = decrypt_book_cipher("3:1:1 1:9:1 1:1:99 0:1 43 1:2:3:4 x", BOOK);
+        assert_eq!(found.len(), 7);
+        assert!(found.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn recovers_running_key_offset_from_corpus() {
+        let corpus = "Call me Ishmael. Some years ago, never mind how long precisely, having little \
+            or no money in my purse, and nothing particular to interest me on shore, I thought I \
+            would sail about a little and see the watery part of the world.";
+        let plain = "Meet the courier at the north gate tonight";
+        let offset = 40;
+        let key: Vec<u8> = letter_values(corpus)[offset..].iter().map(|&k| (26 - k) % 26).collect();
+        let ciphertext = decrypt_vigenere(plain, &key[..letter_values(plain).len()]).unwrap();
+
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let mut top_n = TopN::new(5);
+        crack_running_key(&Session::new(&scorer, &config), &ciphertext, corpus, &mut top_n);
+        let best = top_n.best_result().unwrap();
+        assert!(best.params.starts_with("offset 40 "), "{}", best.params);
+        assert_eq!(best.plaintext_full, plain);
+    }
+
+    #[test]
+    fn stop_flag_ends_running_key_and_book_attacks() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop);
+        let mut top_n = TopN::new(5);
+        crack_running_key(&session, "Phhw wkh frxulhu", BOOK, &mut top_n);
+        crack_running_key_statistical(&session, "Phhw wkh frxulhu", &mut top_n);
+        crack_book_cipher(&session, "1:1:4", BOOK, &mut top_n);
+        assert!(top_n.best_result().is_none());
+    }
+}
+
+// ========== ENIGMA ==========
+
+const ENIGMA_ROTORS: &[(&str, &str, &str)] = &[
+    ("I", "EKMFLGDQVZNTOWYHXUSPAIBRCJ", "Q"),
+    ("II", "AJDKSIRUXBLHWTMCQGZNPYFVOE", "E"),
+    ("III", "BDFHJLCPRTXVZNYEIWGAKMUSQO", "V"),
+    ("IV", "ESOVPZJAYQUIRHXLNFTGKDCMWB", "J"),
+    ("V", "VZBRGITYUPSDNHLXAWMJQOFECK", "Z"),
+    ("VI", "JPGVOUMFYQBENHZRDKASXLICTW", "ZM"),
+    ("VII", "NZJHGRCXMYSWBOUFAIVLPEKQDT", "ZM"),
+    ("VIII", "FKQHTLXOCBJSPDZRAMEWNIUYGV", "ZM"),
+];
+
+// M4 fourth rotors; they sit next to the thin reflector and never step.
+const ENIGMA_GREEK_ROTORS: &[(&str, &str)] = &[
+    ("beta", "LEYJVCNIXWPBQMDRTAKZGFUHOS"),
+    ("gamma", "FSOKANUERHMBTIYCWLQPZXVGJD"),
+];
+
+const ENIGMA_REFLECTORS: &[(&str, &str)] = &[
+    ("A", "EJMZALYXVBWFCRQUONTSPIKHGD"),
+    ("B", "YRUHQSLDPXNGOKMIEBFZCWVJAT"),
+    ("C", "FVPJIAOYEDRZXWGCTKUQSBNMHL"),
+    ("B-thin", "ENKQAUYWJICOPBLMDXZVFTHRGS"),
+    ("C-thin", "RDOBJNTKVEHMLFCWZAXGYIPSUQ"),
+];
+
+const ENIGMA_ATTACK_CANDIDATES: usize = 100;
+const ENIGMA_MAX_PLUGS: usize = 10;
+
+/// Machine type, which decides the rotors and reflectors searched.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EnigmaModel {
+    I,
+    M3,
+    M4,
+}
+
+impl EnigmaModel {
+    /// `I`, `M3` or `M4`, in any case.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "I" => Some(EnigmaModel::I),
+            "M3" => Some(EnigmaModel::M3),
+            "M4" => Some(EnigmaModel::M4),
+            _ => None,
+        }
+    }
+
+    fn rotor_count(&self) -> usize {
+        match self {
+            EnigmaModel::I => 5,
+            EnigmaModel::M3 | EnigmaModel::M4 => 8,
+        }
+    }
+
+    fn reflectors(&self) -> &'static [usize] {
+        match self {
+            EnigmaModel::I => &[1],
+            EnigmaModel::M3 => &[1, 2],
+            EnigmaModel::M4 => &[3],
+        }
+    }
+
+    fn greek_rotors(&self) -> Vec<Option<usize>> {
+        match self {
+            EnigmaModel::M4 => (0..ENIGMA_GREEK_ROTORS.len()).map(Some).collect(),
+            _ => vec![None],
+        }
+    }
+}
+
+/// Slot 0 is the M4 Greek rotor (absent on I/M3), then left, middle, right.
+/// Rings and positions are 0-based (A = 0).
+#[derive(Clone)]
+pub struct EnigmaSettings {
+    reflector: usize,
+    greek: Option<usize>,
+    rotors: [usize; 3],
+    rings: [u8; 4],
+    positions: [u8; 4],
+    plugboard: [u8; 26],
+}
+
+fn identity_plugboard() -> [u8; 26] {
+    let mut plugboard = [0u8; 26];
+    for (i, p) in plugboard.iter_mut().enumerate() {
+        *p = i as u8;
+    }
+    plugboard
+}
+
+fn letter(value: u8) -> char {
+    (b'A' + value) as char
+}
+
+impl EnigmaSettings {
+    /// Settings in the form `UKW-B I-II-III rings AAA pos AAA plugs AB CD`.
+    pub fn describe(&self) -> String {
+        let mut rotors: Vec<&str> = self.rotors.iter().map(|&r| ENIGMA_ROTORS[r].0).collect();
+        let mut rings: String = self.rings[1..].iter().map(|&r| letter(r)).collect();
+        let mut positions: String = self.positions[1..].iter().map(|&p| letter(p)).collect();
+        if let Some(greek) = self.greek {
+            rotors.insert(0, ENIGMA_GREEK_ROTORS[greek].0);
+            rings.insert(0, letter(self.rings[0]));
+            positions.insert(0, letter(self.positions[0]));
+        }
+
+        let plugs: Vec<String> = (0..26u8)
+            .filter(|&a| self.plugboard[a as usize] > a)
+            .map(|a| format!("{}{}", letter(a), letter(self.plugboard[a as usize])))
+            .collect();
+
+        format!(
+            "UKW-{} {} rings {} pos {} plugs {}",
+            ENIGMA_REFLECTORS[self.reflector].0,
+            rotors.join("-"),
+            rings,
+            positions,
+            if plugs.is_empty() { "none".to_string() } else { plugs.join(" ") }
+        )
+    }
+}
+
+struct EnigmaMachine {
+    forward: [[u8; 26]; 4],
+    backward: [[u8; 26]; 4],
+    notches: [[bool; 26]; 4],
+    reflector: [u8; 26],
+    plugboard: [u8; 26],
+    rings: [u8; 4],
+}
+
+fn wiring_table(wiring: &str) -> [u8; 26] {
+    let mut table = [0u8; 26];
+    for (i, b) in wiring.bytes().enumerate() {
+        table[i] = b - b'A';
+    }
+    table
+}
+
+impl EnigmaMachine {
+    fn new(settings: &EnigmaSettings) -> Self {
+        let mut forward = [identity_plugboard(); 4];
+        let mut notches = [[false; 26]; 4];
+
+        if let Some(greek) = settings.greek {
+            forward[0] = wiring_table(ENIGMA_GREEK_ROTORS[greek].1);
+        }
+        for (slot, &rotor) in settings.rotors.iter().enumerate() {
+            let (_, wiring, turnover) = ENIGMA_ROTORS[rotor];
+            forward[slot + 1] = wiring_table(wiring);
+            for b in turnover.bytes() {
+                notches[slot + 1][(b - b'A') as usize] = true;
+            }
+        }
+
+        let mut backward = [[0u8; 26]; 4];
+        for slot in 0..4 {
+            for i in 0..26 {
+                backward[slot][forward[slot][i] as usize] = i as u8;
+            }
+        }
+
+        EnigmaMachine {
+            forward,
+            backward,
+            notches,
+            reflector: wiring_table(ENIGMA_REFLECTORS[settings.reflector].1),
+            plugboard: settings.plugboard,
+            rings: settings.rings,
+        }
+    }
+
+    // Pawl stepping before each key press, including the middle rotor's
+    // double step.
+    fn step(&self, pos: &mut [u8; 4]) {
+        let middle_at_notch = self.notches[2][pos[2] as usize];
+        let right_at_notch = self.notches[3][pos[3] as usize];
+        if middle_at_notch {
+            pos[1] = (pos[1] + 1) % 26;
+        }
+        if middle_at_notch || right_at_notch {
+            pos[2] = (pos[2] + 1) % 26;
+        }
+        pos[3] = (pos[3] + 1) % 26;
+    }
+
+    // Enciphers letter values (0-25) from the given start positions. The
+    // machine is reciprocal, so this also deciphers.
+    fn process(&self, start: [u8; 4], letters: &[u8], out: &mut Vec<u8>) {
+        out.clear();
+        let mut pos = start;
+
+        for &c in letters {
+            self.step(&mut pos);
+            let shifts: [u8; 4] = std::array::from_fn(|slot| (26 + pos[slot] - self.rings[slot]) % 26);
+            let mut x = self.plugboard[c as usize];
+            for (wiring, &shift) in self.forward.iter().zip(&shifts).rev() {
+                x = (wiring[((x + shift) % 26) as usize] + 26 - shift) % 26;
+            }
+            x = self.reflector[x as usize];
+            for (wiring, &shift) in self.backward.iter().zip(&shifts) {
+                x = (wiring[((x + shift) % 26) as usize] + 26 - shift) % 26;
+            }
+            out.push(self.plugboard[x as usize]);
+        }
+    }
+}
+
+/// Enigma is reciprocal: the same settings encrypt and decrypt. Letters keep
+/// their case and everything else passes through without stepping the rotors.
+pub fn decrypt_enigma(text: &str, settings: &EnigmaSettings) -> String {
+    let machine = EnigmaMachine::new(settings);
+    let mut out = Vec::new();
+    machine.process(settings.positions, &letter_values(text), &mut out);
+
+    let mut letters = out.into_iter();
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                let v = letters.next().unwrap_or(0);
+                if c.is_ascii_lowercase() { (b'a' + v) as char } else { (b'A' + v) as char }
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn letters_ioc(letters: &[u8]) -> f64 {
+    let mut counts = [0usize; 26];
+    for &c in letters {
+        counts[c as usize] += 1;
+    }
+    let n = letters.len();
+    if n < 2 {
+        return 0.0;
+    }
+    let pairs: usize = counts.iter().map(|&c| c * c.saturating_sub(1)).sum();
+    pairs as f64 / (n * (n - 1)) as f64
+}
+
+fn rotor_orders(count: usize) -> Vec<[usize; 3]> {
+    let mut orders = Vec::new();
+    for a in 0..count {
+        for b in (0..count).filter(|&b| b != a) {
+            for c in (0..count).filter(|&c| c != a && c != b) {
+                orders.push([a, b, c]);
+            }
+        }
+    }
+    orders
+}
+
+// Step 1: every rotor order, reflector and start position with rings at A
+// and an empty plugboard. The best position by IoC is kept for each order,
+// since the plugboard and ring settings can hide the right one from a
+// global ranking. The M4 Greek rotor never steps but its position still
+// changes the wiring, so it is searched too, at 26 times the cost.
+fn enigma_rotor_search(session: &Session, letters: &[u8], model: EnigmaModel, top_n: &TopN) -> Vec<(f64, EnigmaSettings)> {
+    let mut best: Vec<(f64, EnigmaSettings)> = Vec::new();
+    let mut scratch = Vec::with_capacity(letters.len());
+    let orders = rotor_orders(model.rotor_count());
+    let greek_rotors = model.greek_rotors();
+    let span: u32 = if model == EnigmaModel::M4 { 26 * 26 * 26 * 26 } else { 26 * 26 * 26 };
+    let mut progress = Progress::new((model.reflectors().len() * greek_rotors.len() * orders.len()) as u64 * span as u64);
+
+    'search: for &reflector in model.reflectors() {
+        for &greek in &greek_rotors {
+            for &rotors in &orders {
+                if session.should_stop() {
+                    break 'search;
+                }
+                let mut settings = EnigmaSettings {
+                    reflector,
+                    greek,
+                    rotors,
+                    rings: [0; 4],
+                    positions: [0; 4],
+                    plugboard: identity_plugboard(),
+                };
+                let machine = EnigmaMachine::new(&settings);
+                let mut order_best = f64::NEG_INFINITY;
+
+                for p in 0..span {
+                    if session.should_stop() {
+                        break;
+                    }
+                    let start = [(p / 17576) as u8, (p / 676 % 26) as u8, (p / 26 % 26) as u8, (p % 26) as u8];
+                    machine.process(start, letters, &mut scratch);
+                    let ioc = letters_ioc(&scratch);
+                    if ioc > order_best {
+                        order_best = ioc;
+                        settings.positions = start;
+                    }
+                    progress.tick(session, top_n);
+                }
+
+                if order_best.is_finite() {
+                    best.push((order_best, settings));
+                }
+            }
+        }
+    }
+    progress.finish(session);
+
+    best.sort_by(|a, b| b.0.total_cmp(&a.0));
+    best.truncate(ENIGMA_ATTACK_CANDIDATES);
+    best
+}
+
+fn enigma_fitness(scorer: &Scorer, settings: &EnigmaSettings, letters: &[u8], scratch: &mut Vec<u8>, trigrams: bool) -> f64 {
+    EnigmaMachine::new(settings).process(settings.positions, letters, scratch);
+    if trigrams { scorer.trigram_score(scratch) } else { letters_ioc(scratch) }
+}
+
+// Step 2: the right and middle ring settings only shift turnover points, so
+// each is tried with the start position moved to keep the wiring aligned.
+// Run with IoC before the plugboard is known and with trigrams after.
+fn enigma_ring_search(scorer: &Scorer, settings: &mut EnigmaSettings, letters: &[u8], trigrams: bool) {
+    let mut scratch = Vec::with_capacity(letters.len());
+    let mut best = enigma_fitness(scorer, settings, letters, &mut scratch, trigrams);
+
+    for slot in [3, 2] {
+        let base = settings.clone();
+        for ring in 0..26u8 {
+            let mut candidate = base.clone();
+            candidate.rings[slot] = ring;
+            candidate.positions[slot] = (base.positions[slot] + 26 + ring - base.rings[slot]) % 26;
+            let fitness = enigma_fitness(scorer, &candidate, letters, &mut scratch, trigrams);
+            if fitness > best {
+                best = fitness;
+                *settings = candidate;
+            }
+        }
+    }
+}
+
+// Step 3: greedily add (or replace) plugboard pairs while the fitness
+// improves; IoC first to get the strongest pairs in, then trigrams.
+fn enigma_plugboard_climb(scorer: &Scorer, settings: &mut EnigmaSettings, letters: &[u8]) -> f64 {
+    let mut scratch = Vec::with_capacity(letters.len());
+    let mut best = 0.0;
+
+    for trigrams in [false, true] {
+        best = enigma_fitness(scorer, settings, letters, &mut scratch, trigrams);
+
+        loop {
+            let plugged = (0..26).filter(|&i| settings.plugboard[i] != i as u8).count() / 2;
+            let mut improved: Option<(f64, [u8; 26])> = None;
+
+            for a in 0..26u8 {
+                for b in a + 1..26u8 {
+                    let mut candidate = settings.clone();
+                    // Unplug whatever a and b were connected to, then join them.
+                    for x in [a, b] {
+                        let partner = candidate.plugboard[x as usize];
+                        candidate.plugboard[partner as usize] = partner;
+                        candidate.plugboard[x as usize] = x;
+                    }
+                    candidate.plugboard[a as usize] = b;
+                    candidate.plugboard[b as usize] = a;
+
+                    let pairs = (0..26).filter(|&i| candidate.plugboard[i] != i as u8).count() / 2;
+                    if pairs > ENIGMA_MAX_PLUGS.max(plugged) {
+                        continue;
+                    }
+
+                    let fitness = enigma_fitness(scorer, &candidate, letters, &mut scratch, trigrams);
+                    if fitness > improved.map_or(best, |(f, _)| f) {
+                        improved = Some((fitness, candidate.plugboard));
+                    }
+                }
+            }
+
+            match improved {
+                Some((fitness, plugboard)) => {
+                    best = fitness;
+                    settings.plugboard = plugboard;
+                }
+                None => break,
+            }
+        }
+    }
+
+    best
+}
+
+/// Ciphertext-only attack: rotor order and start positions first, then
+/// ring settings and a hill climb over plugboard pairs.
+pub fn crack_enigma(session: &Session, ciphertext: &str, model: EnigmaModel, top_n: &mut TopN) {
+    let letters = letter_values(ciphertext);
+    if letters.len() < 3 {
+        return;
+    }
+
+    for (_, mut settings) in enigma_rotor_search(session, &letters, model, top_n) {
+        if session.should_stop() {
+            break;
+        }
+        enigma_ring_search(session.scorer, &mut settings, &letters, false);
+        enigma_plugboard_climb(session.scorer, &mut settings, &letters);
+        enigma_ring_search(session.scorer, &mut settings, &letters, true);
+
+        let plain = decrypt_enigma(ciphertext, &settings);
+        let score = session.scorer.score(&plain);
+        top_n.insert_lightweight(score, "Enigma".to_string(), settings.describe(), &plain);
+    }
+}
+
+fn parse_enigma_letters(arg: &str) -> Option<Vec<u8>> {
+    if !arg.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(arg.bytes().map(|b| b.to_ascii_uppercase() - b'A').collect())
+}
+
+/// Builds settings from the `enigma` command's flags. Rotors are given left
+/// to right (`beta,II,IV,I` for an M4); rings and positions as letters in
+/// the same order.
+pub fn parse_enigma_settings(
+    reflector: &str,
+    rotors: &str,
+    rings: &str,
+    positions: &str,
+    plugs: &str,
+) -> std::result::Result<EnigmaSettings, String> {
+    let reflector = ENIGMA_REFLECTORS
+        .iter()
+        .position(|(name, _)| name.eq_ignore_ascii_case(reflector))
+        .ok_or_else(|| format!("unknown reflector '{}'", reflector))?;
+
+    let mut names: Vec<&str> = rotors.split([',', '-']).map(str::trim).collect();
+    let greek = if names.len() == 4 {
+        let name = names.remove(0);
+        Some(
+            ENIGMA_GREEK_ROTORS
+                .iter()
+                .position(|(g, _)| g.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("unknown greek rotor '{}'", name))?,
+        )
+    } else {
+        None
+    };
+    if names.len() != 3 {
+        return Err("expected three rotors (four with a greek rotor)".to_string());
+    }
+    if greek.is_some() != ENIGMA_REFLECTORS[reflector].0.ends_with("-thin") {
+        return Err("a greek rotor needs a thin reflector and vice versa".to_string());
+    }
+
+    let mut slots = [0usize; 3];
+    for (slot, name) in slots.iter_mut().zip(&names) {
+        *slot = ENIGMA_ROTORS
+            .iter()
+            .position(|(r, _, _)| r.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown rotor '{}'", name))?;
+    }
+    if slots[0] == slots[1] || slots[0] == slots[2] || slots[1] == slots[2] {
+        return Err("each rotor can only be used once".to_string());
+    }
+
+    let width = if greek.is_some() { 4 } else { 3 };
+    let to_slots = |arg: &str, what: &str| -> std::result::Result<[u8; 4], String> {
+        let values = parse_enigma_letters(arg)
+            .filter(|v| v.len() == width)
+            .ok_or_else(|| format!("{} must be {} letters", what, width))?;
+        let mut out = [0u8; 4];
+        out[4 - width..].copy_from_slice(&values);
+        Ok(out)
+    };
+
+    let mut plugboard = identity_plugboard();
+    for pair in plugs.split_whitespace() {
+        let values = parse_enigma_letters(pair)
+            .filter(|v| v.len() == 2 && v[0] != v[1])
+            .ok_or_else(|| format!("invalid plug pair '{}'", pair))?;
+        let (a, b) = (values[0] as usize, values[1] as usize);
+        if plugboard[a] != a as u8 || plugboard[b] != b as u8 {
+            return Err(format!("letter in '{}' is already plugged", pair));
+        }
+        plugboard[a] = b as u8;
+        plugboard[b] = a as u8;
+    }
+
+    Ok(EnigmaSettings {
+        reflector,
+        greek,
+        rotors: slots,
+        rings: to_slots(rings, "rings")?,
+        positions: to_slots(positions, "positions")?,
+        plugboard,
+    })
+}
+
+#[cfg(test)]
+mod enigma_tests {
+    use super::*;
+
+    #[test]
+    fn enciphers_the_standard_vector() {
+        let settings = parse_enigma_settings("B", "I,II,III", "AAA", "AAA", "").unwrap();
+        assert_eq!(decrypt_enigma("AAAAA", &settings), "BDZGO");
+        assert_eq!(decrypt_enigma("BDZGO", &settings), "AAAAA");
+        // Beta at A with the thin B reflector wires up like a three-rotor UKW-B.
+        let m4 = parse_enigma_settings("B-thin", "beta,I,II,III", "AAAA", "AAAA", "").unwrap();
+        assert_eq!(decrypt_enigma("AAAAA", &m4), "BDZGO");
+    }
+
+    #[test]
+    fn middle_rotor_double_steps() {
+        let settings = parse_enigma_settings("B", "I,II,III", "AAA", "ADU", "").unwrap();
+        let machine = EnigmaMachine::new(&settings);
+        let mut pos = settings.positions;
+        let mut seen = Vec::new();
+        for _ in 0..3 {
+            machine.step(&mut pos);
+            seen.push(pos[1..].iter().map(|&p| letter(p)).collect::<String>());
+        }
+        assert_eq!(seen, ["ADV", "AEW", "BFX"]);
+    }
+
+    #[test]
+    fn stop_flag_ends_the_rotor_search() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop);
+        let mut top_n = TopN::new(5);
+        crack_enigma(&session, "BDZGOWCXLTKSBTMCDLPBMUQOFXYHCXTGYJFLINHNXSHIUNTHEORXPQPKOVHCBUBTZSZSOOSTGOTFSODBBZZLXLCYZXIFGWFDZEEQIB", EnigmaModel::M4, &mut top_n);
+        assert!(top_n.best_result().is_none());
+    }
+}
+
+// ========== HOMOPHONIC SUBSTITUTION ==========
+
+const HOMOPHONIC_RESTARTS: usize = 8;
+const HOMOPHONIC_ITERATIONS: usize = 300_000;
+const HOMOPHONIC_START_TEMP: f64 = 5.0;
+// Weight of the letter-distribution penalty. Without it the annealing
+// drifts to keys that map most symbols onto e/t/s, which trigrams alone
+// score better than the real plaintext.
+const HOMOPHONIC_KL_WEIGHT: f64 = 4.0;
+
+// Small xorshift generator so the annealing needs no external crate.
+struct Rng(u64);
+
+impl Rng {
+    // Xorshift never leaves zero, so the low bit is forced on.
+    fn new(seed: u64) -> Self {
+        Rng(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x9e37_79b9_7f4a_7c15)
+}
+
+/// Splits homophonic ciphertext into symbols: multi-character
+/// whitespace-separated tokens (e.g. `12 40 7`) are symbols as they stand;
+/// otherwise every non-space character is its own glyph.
+pub fn tokenize_symbols(text: &str) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.iter().any(|w| w.chars().count() > 1) && words.iter().all(|w| w.chars().all(|c| c.is_alphanumeric())) {
+        words.iter().map(|w| w.to_string()).collect()
+    } else {
+        text.chars().filter(|c| !c.is_whitespace()).map(|c| c.to_string()).collect()
+    }
+}
+
+fn homophonic_mapping_label(symbols: &[String], key: &[u8]) -> String {
+    let mut parts = Vec::new();
+    for letter in 0..26u8 {
+        let mapped: Vec<&str> = symbols
+            .iter()
+            .zip(key)
+            .filter(|&(_, &k)| k == letter)
+            .map(|(s, _)| s.as_str())
+            .collect();
+        if !mapped.is_empty() {
+            parts.push(format!("{}={}", (b'a' + letter) as char, mapped.join(",")));
+        }
+    }
+    parts.join(" ")
+}
+
+// Trigram log-likelihood minus a penalty for the KL divergence of the letter
+// counts from the scoring language.
+fn homophonic_fitness(scorer: &Scorer, plain: &[u8]) -> f64 {
+    let english = &scorer.language_model(scorer.primary_language()).unigrams;
+    let mut counts = [0usize; 26];
+    for &p in plain {
+        counts[p as usize] += 1;
+    }
+
+    let n = plain.len() as f64;
+    let kl: f64 = counts
+        .iter()
+        .zip(english)
+        .filter(|&(&c, _)| c > 0)
+        .map(|(&c, &log_e)| {
+            let o = c as f64 / n;
+            o * (o.ln() - log_e)
+        })
+        .sum();
+
+    scorer.trigram_score(plain) - HOMOPHONIC_KL_WEIGHT * n * kl
+}
+
+// One annealing run: each symbol maps to a letter independently (so several
+// symbols may share one), and single-symbol reassignments are accepted by
+// the Metropolis rule.
+fn anneal_homophonic(scorer: &Scorer, cipher: &[usize], symbol_count: usize, rng: &mut Rng) -> (f64, Vec<u8>) {
+    let mut key: Vec<u8> = (0..symbol_count).map(|_| rng.below(26) as u8).collect();
+    let mut plain: Vec<u8> = cipher.iter().map(|&s| key[s]).collect();
+    let mut fitness = homophonic_fitness(scorer, &plain);
+    let mut best = (fitness, key.clone());
+
+    for step in 0..HOMOPHONIC_ITERATIONS {
+        let temp = HOMOPHONIC_START_TEMP * (1.0 - step as f64 / HOMOPHONIC_ITERATIONS as f64);
+        let symbol = rng.below(symbol_count);
+        let old = key[symbol];
+        let new = rng.below(26) as u8;
+        if new == old {
+            continue;
+        }
+
+        key[symbol] = new;
+        for (p, &s) in plain.iter_mut().zip(cipher) {
+            if s == symbol {
+                *p = new;
+            }
+        }
+        let candidate = homophonic_fitness(scorer, &plain);
+        let delta = candidate - fitness;
+
+        if delta >= 0.0 || (temp > 0.0 && rng.unit() < (delta / temp).exp()) {
+            fitness = candidate;
+            if fitness > best.0 {
+                best = (fitness, key.clone());
+            }
+        } else {
+            key[symbol] = old;
+            for (p, &s) in plain.iter_mut().zip(cipher) {
+                if s == symbol {
+                    *p = old;
+                }
+            }
+        }
+    }
+
+    best
+}
+
+/// Solves a homophonic substitution by simulated annealing over
+/// symbol-to-letter mappings. The same `seed` gives the same restarts.
+pub fn crack_homophonic(session: &Session, ciphertext: &str, seed: u64, top_n: &mut TopN) {
+    let tokens = tokenize_symbols(ciphertext);
+    let mut symbols: Vec<String> = Vec::new();
+    let cipher: Vec<usize> = tokens
+        .iter()
+        .map(|t| match symbols.iter().position(|s| s == t) {
+            Some(i) => i,
+            None => {
+                symbols.push(t.clone());
+                symbols.len() - 1
+            }
+        })
+        .collect();
+    if cipher.len() < 3 {
+        return;
+    }
+
+    // Restarts are ranked on the annealing fitness itself; the scorer
+    // has little to go on in unspaced text.
+    let mut rng = Rng::new(seed);
+    let mut restarts = TopN::new(HOMOPHONIC_RESTARTS);
+    for _ in 0..HOMOPHONIC_RESTARTS {
+        if session.should_stop() {
+            break;
+        }
+        let (fitness, key) = anneal_homophonic(session.scorer, &cipher, symbols.len(), &mut rng);
+        let plain: String = cipher.iter().map(|&s| (b'a' + key[s]) as char).collect();
+        restarts.insert_lightweight(
+            fitness.round() as i32,
+            "Homophonic".to_string(),
+            homophonic_mapping_label(&symbols, &key),
+            &plain,
+        );
+    }
+
+    if let Some(mut best) = restarts.into_sorted_vec().into_iter().next() {
+        best.score = session.scorer.score(&best.plaintext_full);
+        top_n.insert(best);
+    }
+}
+
+#[cfg(test)]
+mod homophonic_tests {
+    use super::*;
+
+    const PLAIN: &str = "thereisnothingmoredifficulttotakeinhandmoreperiloustoconductormoreuncertaininitssuccess\
+        thantotaketheleadintheintroductionofaneworderofthingsbecausetheinnovatorhasforenemiesall\
+        thosewhohavedonewellundertheoldconditionsandlukewarmdefendersinthosewhomaydowellunderthe\
+        newthiscoolnessarisespartlyfromfearoftheopponentswhohavethelawsontheirsideandpartlyfrom\
+        theincredulityofmenwhodonotreadilybelieveinnewthingsuntiltheyhavehadalongexperienceofthem";
+
+    // Two-digit codes, with two homophones for the commonest letters.
+    fn encrypt(plain: &str) -> String {
+        let mut counter = [0usize; 26];
+        plain
+            .bytes()
+            .map(|b| {
+                let letter = (b - b'a') as usize;
+                let homophones = if "etaoin".contains(b as char) { 2 } else { 1 };
+                let code = 10 + letter * 3 + counter[letter] % homophones;
+                counter[letter] += 1;
+                code.to_string()
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn agreement(a: &str, b: &str) -> f64 {
+        a.bytes().zip(b.bytes()).filter(|(x, y)| x == y).count() as f64 / a.len() as f64
+    }
+
+    #[test]
+    fn cracks_with_a_fixed_seed() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let mut top_n = TopN::new(1);
+        crack_homophonic(&Session::new(&scorer, &config), &encrypt(PLAIN), 42, &mut top_n);
+        let best = top_n.best_result().unwrap();
+        assert!(agreement(&best.plaintext_full, PLAIN) > 0.9, "{}", best.plaintext_full);
+    }
+
+    #[test]
+    fn same_seed_same_key() {
+        let scorer = Scorer::default();
+        let cipher: Vec<usize> = letter_values("attackatdawnholdthebridge").iter().map(|&l| l as usize).collect();
+        let first = anneal_homophonic(&scorer, &cipher, 26, &mut Rng::new(7));
+        let second = anneal_homophonic(&scorer, &cipher, 26, &mut Rng::new(7));
+        assert_eq!(first.1, second.1);
+    }
+}
+
+// ========== STRADDLING CHECKERBOARD ==========
+
+const CHECKERBOARD_RESTARTS: usize = 4;
+const CHECKERBOARD_ITERATIONS: usize = 20_000;
+// Charged per '.' or '/' in a candidate decryption. Dropping a letter from
+// the trigram score removes about three trigram terms, so without this the
+// climb hides awkward letters in the two non-letter cells.
+const CHECKERBOARD_SYMBOL_PENALTY: f64 = -30.0;
+
+// The 26 letters plus '.' (full stop) and '/' (switch to figures: the next
+// digit is literal).
+const CHECKERBOARD_CELLS: usize = 28;
+
+/// Top row has eight letters, leaving the two blank columns whose digits
+/// prefix the two ten-cell rows below.
+#[derive(Clone)]
+pub struct Checkerboard {
+    blanks: [u8; 2],
+    cells: Vec<char>,
+}
+
+impl Checkerboard {
+    /// Cells filled from the keyword-mixed alphabet, then `.` and `/`. The
+    /// blanks must be two different digits.
+    pub fn from_keyword(keyword: &str, blanks: [u8; 2]) -> CipherResult<Self> {
+        if let Some(&bad) = blanks.iter().find(|&&b| b > 9) {
+            return Err(CipherError::InvalidKey(format!("blank column {} is not a digit", bad)));
+        }
+        if blanks[0] == blanks[1] {
+            return Err(CipherError::InvalidKey("the two blank columns must differ".to_string()));
+        }
+        let mut cells = keyword_alphabet(keyword, false);
+        cells.extend(['.', '/']);
+        Ok(Checkerboard { blanks, cells })
+    }
+
+    // Digit code for each cell index, in layout order.
+    fn codes(&self) -> Vec<String> {
+        let top = (0..10u8).filter(|d| !self.blanks.contains(d)).map(|d| d.to_string());
+        let rows = self.blanks.iter().flat_map(|&b| (0..10).map(move |d| format!("{}{}", b, d)));
+        top.chain(rows).collect()
+    }
+
+    /// The three rows, with `_` for the blank columns of the top row.
+    pub fn describe(&self) -> String {
+        let mut rows = vec![String::new(); 3];
+        let mut idx = 0;
+        for d in 0..10u8 {
+            if self.blanks.contains(&d) {
+                rows[0].push('_');
+            } else {
+                rows[0].push(self.cells[idx]);
+                idx += 1;
+            }
+        }
+        for row in rows.iter_mut().skip(1) {
+            row.extend(&self.cells[idx..idx + 10]);
+            idx += 10;
+        }
+        format!("top {} | {}: {} | {}: {}", rows[0], self.blanks[0], rows[1], self.blanks[1], rows[2])
+    }
+}
+
+/// Writes the text as checkerboard digits; characters with no cell are
+/// dropped.
+pub fn encrypt_checkerboard(text: &str, board: &Checkerboard) -> String {
+    let codes = board.codes();
+    let code_of = |c: char| board.cells.iter().position(|&cell| cell == c).map(|i| codes[i].as_str());
+    let mut out = String::new();
+
+    for c in text.chars() {
+        let c = c.to_ascii_lowercase();
+        if c.is_ascii_digit() {
+            out.push_str(code_of('/').unwrap_or_default());
+            out.push(c);
+        } else if let Some(code) = code_of(c) {
+            out.push_str(code);
+        }
+    }
+    out
+}
+
+// Splits a digit stream into cell indices; a blank digit consumes the
+// following digit as its column. A trailing lone blank digit is dropped.
+fn checkerboard_cells(digits: &[u8], blanks: [u8; 2]) -> Vec<usize> {
+    let top: Vec<u8> = (0..10u8).filter(|d| !blanks.contains(d)).collect();
+    let mut cells = Vec::with_capacity(digits.len());
+    let mut i = 0;
+
+    while i < digits.len() {
+        let d = digits[i];
+        if let Some(row) = blanks.iter().position(|&b| b == d) {
+            if let Some(&col) = digits.get(i + 1) {
+                cells.push(8 + row * 10 + col as usize);
+            }
+            i += 2;
+        } else {
+            cells.push(top.iter().position(|&t| t == d).unwrap_or(0));
+            i += 1;
+        }
+    }
+    cells
+}
+
+/// The digits of `text` as values 0-9; everything else is dropped.
+pub fn digit_values(text: &str) -> Vec<u8> {
+    text.bytes().filter(|b| b.is_ascii_digit()).map(|b| b - b'0').collect()
+}
+
+/// Reads checkerboard digits back into text, ignoring anything that isn't
+/// a digit.
+pub fn decrypt_checkerboard(text: &str, board: &Checkerboard) -> String {
+    let digits = digit_values(text);
+    let top: Vec<u8> = (0..10u8).filter(|d| !board.blanks.contains(d)).collect();
+    let mut out = String::with_capacity(digits.len());
+    let mut figures = false;
+    let mut i = 0;
+
+    while i < digits.len() {
+        let d = digits[i];
+        if figures {
+            out.push((b'0' + d) as char);
+            figures = false;
+            i += 1;
+            continue;
+        }
+
+        // A blank digit is the row prefix of a two-digit code.
+        let (cell, width) = match board.blanks.iter().position(|&b| b == d) {
+            Some(row) => match digits.get(i + 1) {
+                Some(&col) => (8 + row * 10 + col as usize, 2),
+                None => break,
+            },
+            None => (top.iter().position(|&t| t == d).unwrap_or(0), 1),
+        };
+        i += width;
+
+        match board.cells[cell] {
+            '/' => figures = true,
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+// Hill-climbs the layout for fixed blanks: cells are a permutation of the
+// 28 symbols, so swaps keep it a valid board. Fitness is trigram score over
+// the letters.
+fn climb_checkerboard(scorer: &Scorer, cells: &[usize], rng: &mut Rng) -> (f64, Vec<char>) {
+    let symbols: Vec<char> = ('a'..='z').chain(['.', '/']).collect();
+
+    // Start from frequency order: the most common codes get e, t, a, ...
+    let mut counts = [0usize; CHECKERBOARD_CELLS];
+    for &c in cells {
+        counts[c] += 1;
+    }
+    let mut by_freq: Vec<usize> = (0..CHECKERBOARD_CELLS).collect();
+    by_freq.sort_by_key(|&c| std::cmp::Reverse(counts[c]));
+    let english: Vec<char> = "etaoinsrhldcumfpgwybvkxjqz./".chars().collect();
+
+    let fitness_of = |layout: &[char]| -> f64 {
+        let letters: Vec<u8> = cells
+            .iter()
+            .map(|&c| layout[c])
+            .filter(|c| c.is_ascii_lowercase())
+            .map(|c| c as u8 - b'a')
+            .collect();
+        let symbols = cells.len() - letters.len();
+        scorer.trigram_score(&letters) + CHECKERBOARD_SYMBOL_PENALTY * symbols as f64
+    };
+
+    let mut best: (f64, Vec<char>) = (f64::NEG_INFINITY, symbols.clone());
+    for restart in 0..CHECKERBOARD_RESTARTS {
+        let mut layout = vec!['.'; CHECKERBOARD_CELLS];
+        for (rank, &cell) in by_freq.iter().enumerate() {
+            layout[cell] = english[rank];
+        }
+        // Later restarts shake up the frequency guess a little.
+        for _ in 0..restart * 5 {
+            layout.swap(rng.below(CHECKERBOARD_CELLS), rng.below(CHECKERBOARD_CELLS));
+        }
+
+        let mut fitness = fitness_of(&layout);
+        for _ in 0..CHECKERBOARD_ITERATIONS {
+            let (a, b) = (rng.below(CHECKERBOARD_CELLS), rng.below(CHECKERBOARD_CELLS));
+            if a == b {
+                continue;
+            }
+            layout.swap(a, b);
+            let candidate = fitness_of(&layout);
+            if candidate >= fitness {
+                fitness = candidate;
+            } else {
+                layout.swap(a, b);
+            }
+        }
+
+        if fitness > best.0 {
+            best = (fitness, layout);
+        }
+    }
+    best
+}
+
+// Tries every pair of blank digits and keeps the board with the best climb
+// fitness per decoded symbol (pairs split the stream into different numbers
+// of symbols).
+fn solve_checkerboard(session: &Session, digits: &str, rng: &mut Rng) -> Option<(f64, Checkerboard)> {
+    let stream = digit_values(digits);
+    if stream.len() < 4 {
+        return None;
+    }
+
+    let mut best: Option<(f64, Checkerboard)> = None;
+    for a in 0..10u8 {
+        for b in a + 1..10u8 {
+            if session.should_stop() {
+                return best;
+            }
+            let blanks = [a, b];
+            let cells = checkerboard_cells(&stream, blanks);
+            let (fitness, layout) = climb_checkerboard(session.scorer, &cells, rng);
+            let per_symbol = fitness / cells.len().max(1) as f64;
+            if best.as_ref().is_none_or(|(f, _)| per_symbol > *f) {
+                best = Some((per_symbol, Checkerboard { blanks, cells: layout }));
+            }
+        }
+    }
+    best
+}
+
+// The digit streams a VIC-style crack tries: the digits as given, then with
+// a columnar transposition of 2-10 columns undone. Only the columns in their
+// written order are tried; a keyed column order (as in real VIC messages)
+// would multiply the checkerboard search by up to 10! and is not searched.
+fn vic_digit_streams(digits: &str) -> Vec<(String, String)> {
+    let mut inputs = vec![(String::new(), digits.to_string())];
+    for cols in 2..=10 {
+        if let Ok(untransposed) = decrypt_columnar_transposition(digits, &columnar_key(cols)) {
+            inputs.push((format!("{} cols, ", cols), untransposed));
+        }
+    }
+    inputs
+}
+
+/// With `vic`, an unkeyed columnar transposition over the digits (2-10
+/// columns read back in order) is also undone before solving. Keyed column
+/// orders are not searched; undo those first with the known key and
+/// [`decrypt_columnar_transposition`]. Only the fittest board is reported:
+/// the scorer has little to go on in unspaced text, so it can't be trusted
+/// to pick between boards. The climb is seeded from the session's config.
+pub fn crack_checkerboard(session: &Session, ciphertext: &str, vic: bool, top_n: &mut TopN) {
+    let digits: String = ciphertext.chars().filter(|c| c.is_ascii_digit()).collect();
+    let inputs = if vic { vic_digit_streams(&digits) } else { vec![(String::new(), digits)] };
+
+    let mut rng = Rng::new(session.config.seed());
+    let mut best: Option<(f64, String, String, Checkerboard)> = None;
+    for (label, input) in inputs {
+        if session.should_stop() {
+            break;
+        }
+        if let Some((fitness, board)) = solve_checkerboard(session, &input, &mut rng)
+            && best.as_ref().is_none_or(|(f, ..)| fitness > *f)
+        {
+            best = Some((fitness, label, input, board));
+        }
+    }
+
+    if let Some((_, label, input, board)) = best {
+        let plain = decrypt_checkerboard(&input, &board);
+        let score = session.scorer.score(&plain);
+        top_n.insert_lightweight(score, "Checkerboard".to_string(), format!("{}{}", label, board.describe()), &plain);
+    }
+}
+
+#[cfg(test)]
+mod checkerboard_tests {
+    use super::*;
+
+    #[test]
+    fn rejects_bad_blanks() {
+        assert!(Checkerboard::from_keyword("fruit", [3, 3]).is_err());
+        assert!(Checkerboard::from_keyword("fruit", [2, 12]).is_err());
+        assert!(Checkerboard::from_keyword("fruit", [6, 2]).is_ok());
+    }
+
+    #[test]
+    fn round_trips_for_any_blank_pair() {
+        for blanks in [[2, 6], [6, 2], [0, 9], [8, 9]] {
+            let board = Checkerboard::from_keyword("asinorte", blanks).unwrap();
+            let digits = encrypt_checkerboard("attack at dawn.", &board);
+            assert_eq!(decrypt_checkerboard(&digits, &board), "attackatdawn.", "blanks {:?}", blanks);
+        }
+    }
+
+    #[test]
+    fn vic_search_only_undoes_unkeyed_transpositions() {
+        let board = Checkerboard::from_keyword("asinorte", [2, 6]).unwrap();
+        let digits = encrypt_checkerboard("we are discovered flee at once", &board);
+        let streams = |transposed: &str| -> Vec<String> {
+            vic_digit_streams(transposed).into_iter().map(|(_, d)| d).collect()
+        };
+
+        let unkeyed = encrypt_columnar_transposition(&digits, &columnar_key(5)).unwrap();
+        assert!(streams(&unkeyed).contains(&digits));
+
+        let keyed = encrypt_columnar_transposition(&digits, "zebra").unwrap();
+        assert!(!streams(&keyed).contains(&digits));
+    }
+
+    #[test]
+    fn recovers_a_board_with_a_fixed_seed() {
+        let plain = "thereisnothingmoredifficulttotakeinhandmoreperiloustoconductormoreuncertaininitssuccess\
+            thantotaketheleadintheintroductionofaneworderofthingsbecausetheinnovatorhasforenemiesall\
+            thosewhohavedonewellundertheoldconditionsandlukewarmdefendersinthosewhomaydowellunderthenew.";
+        let board = Checkerboard::from_keyword("asinorte", [2, 6]).unwrap();
+        let digits = encrypt_checkerboard(plain, &board);
+
+        let scorer = Scorer::default();
+        let mut config = SearchConfig::default();
+        config.set_from_arg("seed=7").unwrap();
+        let mut top_n = TopN::new(1);
+        crack_checkerboard(&Session::new(&scorer, &config), &digits, false, &mut top_n);
+        let best = top_n.best_result().unwrap();
+        let right = best.plaintext_full.chars().zip(plain.chars()).filter(|(a, b)| a == b).count();
+        assert!(right as f64 > 0.9 * plain.len() as f64, "{}", best.plaintext_full);
+        assert!(best.params.contains("| 2: ") && best.params.contains("| 6: "), "{}", best.params);
+    }
+
+    #[test]
+    fn stop_flag_ends_the_board_search() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop);
+        let board = Checkerboard::from_keyword("asinorte", [2, 6]).unwrap();
+        let mut top_n = TopN::new(1);
+        crack_checkerboard(&session, &encrypt_checkerboard("attack at dawn", &board), true, &mut top_n);
+        assert!(top_n.best_result().is_none());
+    }
+}
+
+// ========== STREAM CIPHERS ==========
+
+const CHAOCIPHER_NODE_LIMIT: usize = 200_000_000;
+
+/// Ciphers whose state evolves letter by letter. Each call starts again from
+/// the keyed state, so one value can encrypt or decrypt many messages.
+pub trait Cipher {
+    /// Enciphers `text` from the keyed state.
+    fn encrypt(&self, text: &str) -> String;
+    /// Undoes [`Cipher::encrypt`] from the same keyed state.
+    fn decrypt(&self, text: &str) -> String;
+}
+
+// Runs `step` over the ASCII letters (as 0-25), keeping case and leaving
+// everything else untouched.
+fn map_letters<F: FnMut(u8) -> u8>(text: &str, mut step: F) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii_alphabetic() {
+                let base = if c.is_ascii_lowercase() { b'a' } else { b'A' };
+                (base + step(c.to_ascii_lowercase() as u8 - b'a')) as char
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// Chaocipher's two wheels, as letter values (A = 0) from the zenith.
+#[derive(Clone)]
+pub struct Chaocipher {
+    left: [u8; 26],
+    right: [u8; 26],
+}
+
+// Left (ciphertext) wheel: bring the letter to the zenith, then move the
+// letter after it down to the nadir.
+fn chao_permute_left(wheel: &mut [u8; 26], index: usize) {
+    wheel.rotate_left(index);
+    let taken = wheel[1];
+    wheel.copy_within(2..14, 1);
+    wheel[13] = taken;
+}
+
+// Right (plaintext) wheel: bring the letter to the zenith, shift one more
+// place, then move the third letter down to the nadir.
+fn chao_permute_right(wheel: &mut [u8; 26], index: usize) {
+    wheel.rotate_left((index + 1) % 26);
+    let taken = wheel[2];
+    wheel.copy_within(3..14, 2);
+    wheel[13] = taken;
+}
+
+impl Chaocipher {
+    /// Wheels given as two permutations of A-Z, starting at the zenith.
+    pub fn new(left: &str, right: &str) -> CipherResult<Self> {
+        let parse = |s: &str| -> CipherResult<[u8; 26]> {
+            let not_permutation = || CipherError::InvalidKey(format!("'{}' is not a permutation of A-Z", s));
+            let values = parse_enigma_letters(s).ok_or_else(not_permutation)?;
+            let mut wheel = [0u8; 26];
+            if values.len() != 26 {
+                return Err(not_permutation());
+            }
+            wheel.copy_from_slice(&values);
+            let mut sorted = wheel;
+            sorted.sort_unstable();
+            if sorted != identity_plugboard() {
+                return Err(not_permutation());
+            }
+            Ok(wheel)
+        };
+        Ok(Chaocipher { left: parse(left)?, right: parse(right)? })
+    }
+
+    /// Left wheel is the keyword-mixed alphabet of the passphrase, right
+    /// wheel the same for the passphrase reversed.
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        let reversed: String = passphrase.chars().rev().collect();
+        let wheel = |alphabet: Vec<char>| -> [u8; 26] {
+            let mut out = [0u8; 26];
+            for (slot, c) in out.iter_mut().zip(alphabet) {
+                *slot = c as u8 - b'a';
+            }
+            out
+        };
+        Chaocipher {
+            left: wheel(keyword_alphabet(passphrase, false)),
+            right: wheel(keyword_alphabet(&reversed, false)),
+        }
+    }
+
+    /// Both wheels as letters, left first.
+    pub fn describe(&self) -> String {
+        let left: String = self.left.iter().map(|&v| letter(v)).collect();
+        let right: String = self.right.iter().map(|&v| letter(v)).collect();
+        format!("left {} right {}", left, right)
+    }
+}
+
+impl Cipher for Chaocipher {
+    fn encrypt(&self, text: &str) -> String {
+        let (mut left, mut right) = (self.left, self.right);
+        map_letters(text, |p| {
+            let index = right.iter().position(|&r| r == p).unwrap_or(0);
+            let c = left[index];
+            chao_permute_left(&mut left, index);
+            chao_permute_right(&mut right, index);
+            c
+        })
+    }
+
+    fn decrypt(&self, text: &str) -> String {
+        let (mut left, mut right) = (self.left, self.right);
+        map_letters(text, |c| {
+            let index = left.iter().position(|&l| l == c).unwrap_or(0);
+            let p = right[index];
+            chao_permute_left(&mut left, index);
+            chao_permute_right(&mut right, index);
+            p
+        })
+    }
+}
+
+// Known-plaintext attack: depth-first reconstruction of both wheels from
+// aligned plaintext/ciphertext letters. The wheels track which starting
+// slot sits where, so every placed letter lands straight in the starting
+// alphabet. Wheels are only defined up to a common rotation, so the first
+// pair goes to the zenith; letters the crib never touches fill the
+// leftover slots alphabetically.
+type ChaoStarts = ([Option<u8>; 26], [Option<u8>; 26]);
+
+struct ChaoSearch<'a> {
+    session: &'a Session<'a>,
+    plain: &'a [u8],
+    cipher: &'a [u8],
+    nodes: usize,
+    stopped: bool,
+}
+
+impl ChaoSearch<'_> {
+    fn run(&mut self, step: usize, wheels: ([u8; 26], [u8; 26]), starts: ChaoStarts) -> Option<ChaoStarts> {
+        self.nodes += 1;
+        if step == self.plain.len() {
+            return Some(starts);
+        }
+        if self.nodes.is_multiple_of(PROGRESS_CHECK_EVERY as usize) && self.session.should_stop() {
+            self.stopped = true;
+        }
+        if self.stopped || self.nodes > CHAOCIPHER_NODE_LIMIT {
+            return None;
+        }
+
+        let (left, right) = wheels;
+        let (left_start, right_start) = starts;
+        let (p, c) = (self.plain[step], self.cipher[step]);
+        let at = |wheel: &[u8; 26], start: &[Option<u8>; 26], value: u8| {
+            start.iter().position(|&s| s == Some(value)).and_then(|o| wheel.iter().position(|&w| w as usize == o))
+        };
+        let free = |i: usize| left_start[left[i] as usize].is_none() && right_start[right[i] as usize].is_none();
+
+        let slots: Vec<usize> = match (at(&right, &right_start, p), at(&left, &left_start, c)) {
+            (Some(i), Some(j)) if i == j => vec![i],
+            (Some(_), Some(_)) => Vec::new(),
+            (Some(i), None) if left_start[left[i] as usize].is_none() => vec![i],
+            (None, Some(j)) if right_start[right[j] as usize].is_none() => vec![j],
+            (Some(_), None) | (None, Some(_)) => Vec::new(),
+            (None, None) if step == 0 => vec![0],
+            (None, None) => (0..26).filter(|&i| free(i)).collect(),
+        };
+
+        for index in slots {
+            let (mut ls, mut rs) = (left_start, right_start);
+            ls[left[index] as usize] = Some(c);
+            rs[right[index] as usize] = Some(p);
+            let (mut l, mut r) = (left, right);
+            chao_permute_left(&mut l, index);
+            chao_permute_right(&mut r, index);
+            if let Some(found) = self.run(step + 1, (l, r), (ls, rs)) {
+                return Some(found);
+            }
+        }
+        None
+    }
+}
+
+/// Recovers wheels that turn `plain` into `cipher` (letter values, A = 0)
+/// by backtracking over the wheel slots. Gives up with `None` after a
+/// fixed number of search nodes or when the session stops.
+pub fn crack_chaocipher_known_plaintext(session: &Session, plain: &[u8], cipher: &[u8]) -> Option<Chaocipher> {
+    let len = plain.len().min(cipher.len());
+    let mut search = ChaoSearch { session, plain: &plain[..len], cipher: &cipher[..len], nodes: 0, stopped: false };
+    let slots = identity_plugboard();
+    let (left, right) = search.run(0, (slots, slots), ([None; 26], [None; 26]))?;
+
+    let complete = |start: [Option<u8>; 26]| -> [u8; 26] {
+        let mut unused = (0..26u8).filter(|v| !start.contains(&Some(*v)));
+        let mut wheel = [0u8; 26];
+        for (slot, value) in wheel.iter_mut().zip(start) {
+            *slot = value.or_else(|| unused.next()).unwrap_or(0);
+        }
+        wheel
+    };
+    Some(Chaocipher { left: complete(left), right: complete(right) })
+}
+
+// Solitaire (Pontifex): cards 1-52 in bridge order, jokers A = 53 and B = 54.
+const SOLITAIRE_JOKER_A: u8 = 53;
+const SOLITAIRE_JOKER_B: u8 = 54;
+
+/// Solitaire (Pontifex) deck state. Cards are 1-52 in bridge order, jokers
+/// 53 and 54.
+#[derive(Clone)]
+pub struct Solitaire {
+    deck: Vec<u8>,
+}
+
+impl Solitaire {
+    /// The deck in order, jokers last.
+    pub fn unkeyed() -> Self {
+        Solitaire { deck: (1..=54).collect() }
+    }
+
+    /// Keys the deck by running the generator once per passphrase letter and
+    /// replacing the output step with a count cut on the letter's value.
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        let mut solitaire = Self::unkeyed();
+        for b in passphrase.bytes().filter(u8::is_ascii_alphabetic) {
+            solitaire.shuffle();
+            solitaire.count_cut((b.to_ascii_uppercase() - b'A' + 1) as usize);
+        }
+        solitaire
+    }
+
+    fn move_down(&mut self, card: u8, places: usize) {
+        let mut i = self.deck.iter().position(|&c| c == card).unwrap_or(0);
+        for _ in 0..places {
+            if i == self.deck.len() - 1 {
+                let joker = self.deck.remove(i);
+                self.deck.insert(1, joker);
+                i = 1;
+            } else {
+                self.deck.swap(i, i + 1);
+                i += 1;
+            }
+        }
+    }
+
+    fn count_cut(&mut self, count: usize) {
+        let last = self.deck.len() - 1;
+        self.deck[..last].rotate_left(count.min(last));
+    }
+
+    fn card_value(card: u8) -> usize {
+        card.min(SOLITAIRE_JOKER_A) as usize
+    }
+
+    // Steps 1-4: joker moves, triple cut, count cut on the bottom card.
+    fn shuffle(&mut self) {
+        self.move_down(SOLITAIRE_JOKER_A, 1);
+        self.move_down(SOLITAIRE_JOKER_B, 2);
+
+        let a = self.deck.iter().position(|&c| c == SOLITAIRE_JOKER_A).unwrap_or(0);
+        let b = self.deck.iter().position(|&c| c == SOLITAIRE_JOKER_B).unwrap_or(0);
+        let (top, bottom) = (a.min(b), a.max(b));
+        let mut cut = self.deck[bottom + 1..].to_vec();
+        cut.extend_from_slice(&self.deck[top..=bottom]);
+        cut.extend_from_slice(&self.deck[..top]);
+        self.deck = cut;
+
+        let bottom_card = self.deck[self.deck.len() - 1];
+        self.count_cut(Self::card_value(bottom_card));
+    }
+
+    // Keystream values 1-26, skipping jokers in the output position.
+    fn keystream(&self, len: usize) -> Vec<u8> {
+        let mut state = self.clone();
+        let mut stream = Vec::with_capacity(len);
+        while stream.len() < len {
+            state.shuffle();
+            let card = state.deck[Self::card_value(state.deck[0])];
+            if card < SOLITAIRE_JOKER_A {
+                stream.push((card - 1) % 26 + 1);
+            }
+        }
+        stream
+    }
+
+    /// The deck in order, cards by number and jokers as A and B.
+    pub fn describe(&self) -> String {
+        self.deck
+            .iter()
+            .map(|&c| match c {
+                SOLITAIRE_JOKER_A => "A".to_string(),
+                SOLITAIRE_JOKER_B => "B".to_string(),
+                _ => c.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Cipher for Solitaire {
+    // The classic message format: letters only, padded with X to a multiple
+    // of five and written in groups of five.
+    fn encrypt(&self, text: &str) -> String {
+        let mut letters: Vec<u8> = text.bytes().filter(u8::is_ascii_alphabetic).map(|b| b.to_ascii_uppercase() - b'A').collect();
+        while !letters.len().is_multiple_of(5) {
+            letters.push(b'X' - b'A');
+        }
+        let stream = self.keystream(letters.len());
+        let encrypted: Vec<char> = letters.iter().zip(&stream).map(|(&p, &k)| letter((p + k) % 26)).collect();
+        encrypted.chunks(5).map(|group| group.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+    }
+
+    fn decrypt(&self, text: &str) -> String {
+        let count = text.bytes().filter(u8::is_ascii_alphabetic).count();
+        let mut stream = self.keystream(count).into_iter();
+        map_letters(text, |c| (c + 26 - stream.next().unwrap_or(0)) % 26)
+    }
+}
+
+/// Both ciphers are keyed from a passphrase, so the cheap attack is a
+/// dictionary run over the common words.
+pub fn crack_passphrase_stream_ciphers(session: &Session, ciphertext: &str, top_n: &mut TopN) {
+    let mut tried: Vec<&str> = Vec::new();
+    for &word in COMMON_WORDS {
+        if session.should_stop() {
+            break;
+        }
+        if tried.contains(&word) {
+            continue;
+        }
+        tried.push(word);
+
+        let plain = Chaocipher::from_passphrase(word).decrypt(ciphertext);
+        let score = session.scorer.score(&plain);
+        top_n.insert_lightweight(score, "Chaocipher".to_string(), format!("passphrase: {}", word), &plain);
+
+        let plain = Solitaire::from_passphrase(word).decrypt(ciphertext);
+        let score = session.scorer.score(&plain);
+        top_n.insert_lightweight(score, "Solitaire".to_string(), format!("passphrase: {}", word), &plain);
+    }
+}
+
+#[cfg(test)]
+mod stream_tests {
+    use super::*;
+
+    const BYRNE_LEFT: &str = "HXUCZVAMDSLKPEFJRIGTWOBNYQ";
+    const BYRNE_RIGHT: &str = "PTLNBQDEOYSFAVZKGJRIHWXUMC";
+
+    #[test]
+    fn solitaire_matches_the_published_vectors() {
+        assert_eq!(Solitaire::unkeyed().encrypt("AAAAAAAAAA"), "EXKYI ZSGEH");
+        assert_eq!(Solitaire::from_passphrase("FOO").encrypt("AAAAAAAAAAAAAAA"), "ITHZU JIWGR FARMW");
+        assert_eq!(Solitaire::from_passphrase("FOO").decrypt("ITHZU JIWGR FARMW"), "AAAAA AAAAA AAAAA");
+    }
+
+    #[test]
+    fn chaocipher_matches_byrnes_example() {
+        let cipher = Chaocipher::new(BYRNE_LEFT, BYRNE_RIGHT).unwrap();
+        assert_eq!(cipher.encrypt("WELLDONEISBETTERTHANWELLSAID"), "OAHQHCNYNXTSZJRRHJBYHQKSOUJY");
+        assert_eq!(cipher.decrypt("OAHQHCNYNXTSZJRRHJBYHQKSOUJY"), "WELLDONEISBETTERTHANWELLSAID");
+    }
+
+    #[test]
+    fn recovers_wheels_from_a_crib() {
+        let cipher = Chaocipher::new(BYRNE_LEFT, BYRNE_RIGHT).unwrap();
+        let message = "Well done is better than well said, and the deed outlasts the boast.";
+        let ciphertext = cipher.encrypt(message);
+
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let session = Session::new(&scorer, &config);
+        // Twenty letters solve in well under a second; the full 28-letter
+        // sentence needs about a hundred million search nodes.
+        let crib = letter_values("WELLDONEISBETTERTHAN");
+        let found = crack_chaocipher_known_plaintext(&session, &crib, &letter_values(&ciphertext)).unwrap();
+        // Letters outside the crib are placed arbitrarily, so only the
+        // crib's span is sure to come back.
+        assert!(found.decrypt(&ciphertext).starts_with("Well done is better than"));
+    }
+
+    #[test]
+    fn stop_flag_ends_the_wheel_search() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop);
+        let crib = letter_values("WELLDONEISBETTERTHANWELLSAID");
+        let cipher = letter_values("OAHQHCNYNXTSZJRRHJBYHQKSOUJY");
+        assert!(crack_chaocipher_known_plaintext(&session, &crib, &cipher).is_none());
+    }
+}
+
+// ========== ENCODING LAYERS ==========
+
+const MAX_ENCODING_LAYERS: usize = 4;
+
+const MORSE_TABLE: &[(&str, char)] = &[
+    (".-", 'a'), ("-...", 'b'), ("-.-.", 'c'), ("-..", 'd'), (".", 'e'), ("..-.", 'f'),
+    ("--.", 'g'), ("....", 'h'), ("..", 'i'), (".---", 'j'), ("-.-", 'k'), (".-..", 'l'),
+    ("--", 'm'), ("-.", 'n'), ("---", 'o'), (".--.", 'p'), ("--.-", 'q'), (".-.", 'r'),
+    ("...", 's'), ("-", 't'), ("..-", 'u'), ("...-", 'v'), (".--", 'w'), ("-..-", 'x'),
+    ("-.--", 'y'), ("--..", 'z'), ("-----", '0'), (".----", '1'), ("..---", '2'),
+    ("...--", '3'), ("....-", '4'), (".....", '5'), ("-....", '6'), ("--...", '7'),
+    ("---..", '8'), ("----.", '9'), (".-.-.-", '.'), ("--..--", ','), ("..--..", '?'),
+    (".----.", '\''), ("-.-.--", '!'), ("-..-.", '/'), ("-.--.", '('), ("-.--.-", ')'),
+    ("---...", ':'), ("-...-", '='), (".-.-.", '+'), ("-....-", '-'), (".-..-.", '"'),
+    (".--.-.", '@'), ("..--.-", '_'), ("-.-.-.", ';'),
+];
+
+type Decoder = fn(&str) -> Option<String>;
+
+const ENCODINGS: &[(&str, Decoder)] = &[
+    ("base64", decode_base64),
+    ("base32", decode_base32),
+    ("hex", decode_hex),
+    ("binary", decode_binary),
+    ("decimal", decode_decimal),
+    ("morse", decode_morse),
+    ("url", decode_url),
+];
+
+/// Text recovered by peeling encodings off the input.
+#[derive(Clone)]
+pub struct DecodedInput {
+    /// Encodings removed, outermost first.
+    pub layers: Vec<&'static str>,
+    /// The decoded text.
+    pub text: String,
+}
+
+// Only accept decodings that look like text; random bytes from a lucky
+// charset match are thrown away here.
+fn bytes_to_text(bytes: Vec<u8>) -> Option<String> {
+    let text = String::from_utf8(bytes).ok()?;
+    if text.trim().is_empty() || text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return None;
+    }
+    Some(text)
+}
+
+fn compact(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+/// Hex digits (whitespace and a `0x` prefix allowed) as bytes.
+pub fn hex_to_bytes(text: &str) -> Option<Vec<u8>> {
+    let clean = compact(text);
+    let clean = clean.strip_prefix("0x").unwrap_or(&clean);
+    if clean.len() < 2 || !clean.len().is_multiple_of(2) || !clean.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    (0..clean.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&clean[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Base64 (whitespace allowed) as bytes, in either the standard (`+/`) or
+/// the URL-safe (`-_`) alphabet. Text that mixes the two is rejected.
+pub fn base64_to_bytes(text: &str) -> Option<Vec<u8>> {
+    let clean = compact(text);
+    let data = clean.trim_end_matches('=');
+    if data.len() < 4 || data.len() % 4 == 1 || clean.len() - data.len() > 2 {
+        return None;
+    }
+    let (plus, slash) = if data.contains(['-', '_']) { ('-', '_') } else { ('+', '/') };
+
+    let mut bits = 0u32;
+    let mut nbits = 0;
+    let mut bytes = Vec::with_capacity(data.len() * 3 / 4);
+
+    for c in data.chars() {
+        let value = match c {
+            'A'..='Z' => c as u32 - 'A' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 26,
+            '0'..='9' => c as u32 - '0' as u32 + 52,
+            c if c == plus => 62,
+            c if c == slash => 63,
+            _ => return None,
+        };
+        bits = (bits << 6) | value;
+        nbits += 6;
+        if nbits >= 8 {
+            nbits -= 8;
+            bytes.push((bits >> nbits) as u8);
+            bits &= (1 << nbits) - 1;
+        }
+    }
+
+    Some(bytes)
+}
+
+fn decode_hex(text: &str) -> Option<String> {
+    bytes_to_text(hex_to_bytes(text)?)
+}
+
+fn decode_base64(text: &str) -> Option<String> {
+    bytes_to_text(base64_to_bytes(text)?)
+}
+
+fn decode_base32(text: &str) -> Option<String> {
+    let clean = compact(text).to_ascii_uppercase();
+    let data = clean.trim_end_matches('=');
+    if data.len() < 4 || clean.len() - data.len() > 6 || !matches!(data.len() % 8, 0 | 2 | 4 | 5 | 7) {
+        return None;
+    }
+
+    let mut bits = 0u64;
+    let mut nbits = 0;
+    let mut bytes = Vec::with_capacity(data.len() * 5 / 8);
+
+    for c in data.chars() {
+        let value = match c {
+            'A'..='Z' => c as u64 - 'A' as u64,
+            '2'..='7' => c as u64 - '2' as u64 + 26,
+            _ => return None,
+        };
+        bits = (bits << 5) | value;
+        nbits += 5;
+        if nbits >= 8 {
+            nbits -= 8;
+            bytes.push((bits >> nbits) as u8);
+            bits &= (1 << nbits) - 1;
+        }
+    }
+
+    bytes_to_text(bytes)
+}
+
+fn decode_binary(text: &str) -> Option<String> {
+    let clean = compact(text);
+    if clean.len() < 8 || !clean.len().is_multiple_of(8) || !clean.chars().all(|c| c == '0' || c == '1') {
+        return None;
+    }
+
+    let bytes = (0..clean.len())
+        .step_by(8)
+        .map(|i| u8::from_str_radix(&clean[i..i + 8], 2).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes_to_text(bytes)
+}
+
+fn decode_decimal(text: &str) -> Option<String> {
+    let tokens: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+        .collect();
+    if tokens.len() < 2 || !tokens.iter().all(|t| t.chars().all(|c| c.is_ascii_digit())) {
+        return None;
+    }
+
+    let bytes = tokens
+        .iter()
+        .map(|t| t.parse::<u8>().ok())
+        .collect::<Option<Vec<u8>>>()?;
+    bytes_to_text(bytes)
+}
+
+// Letters are separated by spaces and words by '/' (or '|'); '_' is accepted
+// as a dash.
+fn decode_morse(text: &str) -> Option<String> {
+    let trimmed = text.trim();
+    if !trimmed.contains(['.', '-', '_'])
+        || !trimmed.chars().all(|c| matches!(c, '.' | '-' | '_' | '/' | '|') || c.is_whitespace())
+    {
+        return None;
+    }
+
+    let mut words = Vec::new();
+    for word in trimmed.split(['/', '|']) {
+        let mut decoded = String::new();
+        for code in word.split_whitespace() {
+            let code = code.replace('_', "-");
+            let (_, letter) = MORSE_TABLE.iter().find(|(c, _)| *c == code)?;
+            decoded.push(*letter);
+        }
+        if !decoded.is_empty() {
+            words.push(decoded);
+        }
+    }
+
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+fn decode_url(text: &str) -> Option<String> {
+    if !text.contains('%') {
+        return None;
+    }
+
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = text.get(i + 1..i + 3)?;
+                out.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            b'+' => {
+                out.push(b' ');
+                i += 1;
+            }
+            b => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    bytes_to_text(out)
+}
+
+/// Breadth-first peel of encoding layers. Every successful decoding is
+/// returned (not just the deepest) since the classical cipher may sit
+/// underneath any of them.
+pub fn peel_encodings(text: &str) -> Vec<DecodedInput> {
+    let mut found: Vec<DecodedInput> = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    seen.insert(text.to_string());
+
+    let mut frontier = vec![DecodedInput { layers: Vec::new(), text: text.to_string() }];
+
+    for _ in 0..MAX_ENCODING_LAYERS {
+        let mut next = Vec::new();
+
+        for input in &frontier {
+            for (name, decode) in ENCODINGS {
+                if let Some(decoded) = decode(&input.text) {
+                    if decoded == input.text || !seen.insert(decoded.clone()) {
+                        continue;
+                    }
+                    let mut layers = input.layers.clone();
+                    layers.push(*name);
+                    next.push(DecodedInput { layers, text: decoded });
+                }
+            }
+        }
+
+        if next.is_empty() {
+            break;
+        }
+        found.extend(next.iter().cloned());
+        frontier = next;
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::*;
+
+    #[test]
+    fn base64_uses_one_alphabet_per_input() {
+        assert_eq!(base64_to_bytes("aGk/Pz8/").unwrap(), b"hi????");
+        assert_eq!(base64_to_bytes("aGk_Pz8_").unwrap(), b"hi????");
+        assert_eq!(base64_to_bytes("aGk+Pj4/").unwrap(), b"hi>>>?");
+        assert_eq!(base64_to_bytes("aGk-Pj4_").unwrap(), b"hi>>>?");
+        assert_eq!(base64_to_bytes("aGk/Pz8_"), None);
+        assert_eq!(base64_to_bytes("aGk+Pj4_"), None);
+    }
+
+    #[test]
+    fn decoders_read_their_own_format() {
+        assert_eq!(decode_base32("MZXW6YTBOI======").as_deref(), Some("foobar"));
+        assert_eq!(decode_binary("01101000 01101001").as_deref(), Some("hi"));
+        assert_eq!(decode_decimal("104, 105").as_deref(), Some("hi"));
+        assert_eq!(decode_hex("0x68 69").as_deref(), Some("hi"));
+        assert_eq!(decode_morse(".... .. / - .... . .-. .").as_deref(), Some("hi there"));
+        assert_eq!(decode_morse("...._ ..").as_deref(), Some("4i"));
+        assert_eq!(decode_morse("........"), None);
+        assert_eq!(decode_url("attack%20at+dawn%21").as_deref(), Some("attack at dawn!"));
+        assert_eq!(decode_url("100%"), None);
+    }
+
+    #[test]
+    fn peels_nested_layers() {
+        let decoded = peel_encodings("NjM2ZjZkNmQ2NTZlNzQ=");
+        assert!(decoded.iter().any(|d| d.layers == ["base64"] && d.text == "636f6d6d656e74"));
+        assert!(decoded.iter().any(|d| d.layers == ["base64", "hex"] && d.text == "comment"));
+    }
+
+    #[test]
+    fn plain_english_has_no_layers() {
+        for text in ["Meet me at the library at noon.", "attack at dawn", "The quick brown fox jumps over the lazy dog"] {
+            assert!(peel_encodings(text).is_empty(), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn cracks_under_a_layer() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let session = Session::new(&scorer, &config);
+        let mut top_n = TopN::new(5);
+        crack_with_layers(&session, "V2toIHZoZnVodyBsdiB2ZGlo", &mut top_n, |text, top_n| {
+            crack_specific_cipher(&session, 1, text, top_n)
+        });
+        let best = top_n.best_result().unwrap();
+        assert_eq!(best.plaintext_full, "The secret is safe");
+        assert_eq!(best.params, "[base64] shift 3");
+    }
+}
+
+// ========== SCORING FUNCTION ==========
+
+/// Heuristic English score: frequent letters, common words and dictionary
+/// coverage. Higher is better; it is not normalized.
+pub fn score_english(text: &str) -> i32 {
+    english_score(text.as_bytes(), dictionary())
+}
+
+// Works on raw bytes without allocating, so the key-space sweeps can score
+// straight out of their scratch buffer.
+fn english_score(text: &[u8], dict: &Dictionary) -> i32 {
+    let mut freq = [0i32; 26];
+
+    for &b in text.iter().filter(|b| b.is_ascii_alphabetic()) {
+        freq[(b.to_ascii_lowercase() - b'a') as usize] += 1;
+    }
+
+    let mut score = 0;
+
+    for b in FREQ.bytes() {
+        score += freq[(b - b'a') as usize];
+    }
+
+    let common = common_words();
+    for w in text
+        .split(|b| !b.is_ascii_alphabetic())
+        .filter(|w| w.len() >= 3)
+    {
+        if common.contains(w.iter().copied()) {
+            score += 10;
+        }
+    }
+
+    // Dictionary validation bonus
+    let dict_score = valid_word_percent(text, dict);
+    score += dict_score * 2;
+
+    score
+}
+
+/// Chance that two letters picked at random are equal: about 0.067 for
+/// English, 0.038 for random letters.
+pub fn index_of_coincidence(text: &str) -> f64 {
+    let mut counts = [0usize; 26];
+    for c in text.chars().filter(|c| c.is_ascii_alphabetic()) {
+        counts[(c.to_ascii_lowercase() as u8 - b'a') as usize] += 1;
+    }
+
+    let n: usize = counts.iter().sum();
+    if n < 2 {
+        return 0.0;
+    }
+    let pairs: usize = counts.iter().map(|&c| c * c.saturating_sub(1)).sum();
+    pairs as f64 / (n * (n - 1)) as f64
+}
+
+// Letter bigram counts (spaces and punctuation skipped) from a sample of
+// English prose; see data/english_bigrams.txt.
+const ENGLISH_BIGRAMS: &str = include_str!("../data/english_bigrams.txt");
+
+struct BigramModel {
+    start: [f64; 26],
+    next: [[f64; 26]; 26],
+}
+
+impl BigramModel {
+    fn english() -> &'static BigramModel {
+        static MODEL: std::sync::OnceLock<BigramModel> = std::sync::OnceLock::new();
+        MODEL.get_or_init(|| {
+            let mut counts = [[1.0f64; 26]; 26];
+            for line in ENGLISH_BIGRAMS.lines() {
+                let Some((pair, count)) = line.split_once(' ') else { continue };
+                let pair = pair.as_bytes();
+                if let (2, Ok(count)) = (pair.len(), count.trim().parse::<f64>()) {
+                    counts[(pair[0] - b'a') as usize][(pair[1] - b'a') as usize] += count;
+                }
+            }
+
+            let total: f64 = counts.iter().flatten().sum();
+            let mut model = BigramModel { start: [0.0; 26], next: [[0.0; 26]; 26] };
+            for (a, row_counts) in counts.iter().enumerate() {
+                let row: f64 = row_counts.iter().sum();
+                model.start[a] = (row / total).ln();
+                for (b, &count) in row_counts.iter().enumerate() {
+                    model.next[a][b] = (count / row).ln();
+                }
+            }
+            model
+        })
+    }
+
+    // From a trained model's table of bigram log-probabilities.
+    fn from_trained(trained: &[f64]) -> BigramModel {
+        let mut model = BigramModel { start: [0.0; 26], next: [[0.0; 26]; 26] };
+        let total: f64 = trained.iter().map(|lp| lp.exp()).sum();
+        for (a, row) in trained.chunks(26).enumerate() {
+            let row_total: f64 = row.iter().map(|lp| lp.exp()).sum();
+            model.start[a] = (row_total / total).ln();
+            for (b, &lp) in row.iter().enumerate() {
+                model.next[a][b] = lp - row_total.ln();
+            }
+        }
+        model
+    }
+}
+
+// Letter trigram counts within runs of letters, one file per language; see
+// data/*_trigrams.txt. Missing trigrams get a count of 0.5.
+const ENGLISH_TRIGRAMS: &str = include_str!("../data/english_trigrams.txt");
+const FRENCH_TRIGRAMS: &str = include_str!("../data/french_trigrams.txt");
+const GERMAN_TRIGRAMS: &str = include_str!("../data/german_trigrams.txt");
+const SPANISH_TRIGRAMS: &str = include_str!("../data/spanish_trigrams.txt");
+const ITALIAN_TRIGRAMS: &str = include_str!("../data/italian_trigrams.txt");
+
+fn trigram_counts(data: &str) -> Vec<f64> {
+    let mut counts = vec![0.5f64; 26 * 26 * 26];
+    for line in data.lines() {
+        let Some((tri, count)) = line.split_once(' ') else { continue };
+        let tri = tri.as_bytes();
+        if let (3, Ok(count)) = (tri.len(), count.trim().parse::<f64>()) {
+            let idx = (tri[0] - b'a') as usize * 676 + (tri[1] - b'a') as usize * 26 + (tri[2] - b'a') as usize;
+            counts[idx] += count;
+        }
+    }
+    counts
+}
+
+// ========== LANGUAGES ==========
+
+// Most common words in each language's sample (man pages and program
+// messages, English-looking words removed); see data/*_words.txt.
+const FRENCH_WORDS: &str = include_str!("../data/french_words.txt");
+const GERMAN_WORDS: &str = include_str!("../data/german_words.txt");
+const SPANISH_WORDS: &str = include_str!("../data/spanish_words.txt");
+const ITALIAN_WORDS: &str = include_str!("../data/italian_words.txt");
+
+/// A language the scorers know.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Language {
+    English,
+    French,
+    German,
+    Spanish,
+    Italian,
+    // A model built by the `train` command and loaded with `--model`.
+    Custom,
+}
+
+const LANGUAGES: [Language; 5] = [Language::English, Language::French, Language::German, Language::Spanish, Language::Italian];
+
+impl Language {
+    /// Parses a code such as `en` or `fr`.
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "en" | "english" => Some(Language::English),
+            "fr" | "french" => Some(Language::French),
+            "de" | "german" => Some(Language::German),
+            "es" | "spanish" => Some(Language::Spanish),
+            "it" | "italian" => Some(Language::Italian),
+            _ => None,
+        }
+    }
+
+    /// Two-letter code, e.g. `de`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Spanish => "es",
+            Language::Italian => "it",
+            Language::Custom => "custom",
+        }
+    }
+
+    /// English name of the language.
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::French => "French",
+            Language::German => "German",
+            Language::Spanish => "Spanish",
+            Language::Italian => "Italian",
+            Language::Custom => "trained model",
+        }
+    }
+
+    // The twelve most frequent letters, as FREQ is for English.
+    fn frequent_letters(self) -> &'static str {
+        match self {
+            Language::English => FREQ,
+            Language::French => "esiratnoludc",
+            Language::German => "enitrsaduloh",
+            Language::Spanish => "eaonrisdlctu",
+            Language::Italian => "eioanlrtscdp",
+            Language::Custom => FREQ,
+        }
+    }
+
+    fn trigram_data(self) -> &'static str {
+        match self {
+            Language::English => ENGLISH_TRIGRAMS,
+            Language::French => FRENCH_TRIGRAMS,
+            Language::German => GERMAN_TRIGRAMS,
+            Language::Spanish => SPANISH_TRIGRAMS,
+            Language::Italian => ITALIAN_TRIGRAMS,
+            Language::Custom => ENGLISH_TRIGRAMS,
+        }
+    }
+
+    fn words(self) -> Vec<&'static str> {
+        match self {
+            Language::English => COMMON_WORDS.to_vec(),
+            Language::French => FRENCH_WORDS.lines().collect(),
+            Language::German => GERMAN_WORDS.lines().collect(),
+            Language::Spanish => SPANISH_WORDS.lines().collect(),
+            Language::Italian => ITALIAN_WORDS.lines().collect(),
+            Language::Custom => COMMON_WORDS.to_vec(),
+        }
+    }
+}
+
+/// Log-probability tables plus the word list the scorer checks against, for
+/// a built-in language or one trained with [`encode_model`].
+// Trained models also carry bigram and quadgram tables; `frequent` is the
+// twelve most common letters and `top_words` the DETECTION_WORDS most
+// common words.
+pub struct LanguageModel {
+    unigrams: [f64; 26],
+    bigrams: Option<Vec<f64>>,
+    trigrams: Vec<f64>,
+    quadgrams: Option<Vec<f64>>,
+    quadgram_floor: f64,
+    frequent: String,
+    words: std::collections::HashSet<String>,
+    top_words: std::collections::HashSet<String>,
+}
+
+// Every language gets a word list this long for language detection, the
+// size of the smallest built-in list, so no language wins on list size.
+const DETECTION_WORDS: usize = COMMON_WORDS.len();
+
+// The built-in model for a language; Custom falls back to English.
+fn builtin_model(language: Language) -> &'static LanguageModel {
+    static MODELS: std::sync::OnceLock<Vec<LanguageModel>> = std::sync::OnceLock::new();
+    let models = MODELS.get_or_init(|| {
+        LANGUAGES
+            .iter()
+            .map(|&lang| {
+                let counts = trigram_counts(lang.trigram_data());
+                let total: f64 = counts.iter().sum();
+
+                // Letter frequencies are the trigram counts summed by first letter.
+                let mut unigrams = [0.0; 26];
+                for (i, row) in counts.chunks(676).enumerate() {
+                    unigrams[i] = (row.iter().sum::<f64>() / total).ln();
+                }
+
+                LanguageModel {
+                    unigrams,
+                    bigrams: None,
+                    trigrams: counts.iter().map(|c| (c / total).ln()).collect(),
+                    quadgrams: None,
+                    quadgram_floor: f64::NEG_INFINITY,
+                    frequent: lang.frequent_letters().to_string(),
+                    words: lang.words().into_iter().map(String::from).collect(),
+                    top_words: lang.words().into_iter().take(DETECTION_WORDS).map(String::from).collect(),
+                }
+            })
+            .collect()
+    });
+    models.get(language as usize).unwrap_or(&models[0])
+}
+
+/// Which language candidates are scored against: one fixed language, or every
+/// language with the best match winning (`--lang auto`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LanguageChoice {
+    Fixed(Language),
+    Auto,
+}
+
+/// score_english's recipe with another language's letters and words, using
+/// the built-in tables. Accents are folded first so words like "été" aren't
+/// split apart.
+pub fn score_language(text: &str, language: Language) -> i32 {
+    Scorer::default().score_language(text, language)
+}
+
+/// Scores candidate plaintexts: the language choice, an optional trained
+/// model (scored as [`Language::Custom`]) and the dictionary behind the
+/// English word checks. The default scores English with the built-in tables.
+pub struct Scorer {
+    language: LanguageChoice,
+    model: Option<LanguageModel>,
+    // From the trained model's bigram table.
+    bigrams: Option<BigramModel>,
+    // The built-in dictionary plus extra words, if any were given.
+    dictionary: Option<Dictionary>,
+}
+
+impl Default for Scorer {
+    fn default() -> Self {
+        Scorer { language: LanguageChoice::Fixed(Language::English), model: None, bigrams: None, dictionary: None }
+    }
+}
+
+impl Scorer {
+    /// `model` is a trained model, scored as [`Language::Custom`] and tried
+    /// alongside the built-in languages in auto mode; `extra_words` are added
+    /// to the English dictionary.
+    pub fn new(language: LanguageChoice, model: Option<LanguageModel>, extra_words: &[String]) -> Self {
+        let dictionary = (!extra_words.is_empty())
+            .then(|| Dictionary::build(ENGLISH_WORDS.lines().chain(extra_words.iter().map(String::as_str))));
+        Scorer {
+            language,
+            bigrams: model.as_ref().and_then(|m| m.bigrams.as_deref()).map(BigramModel::from_trained),
+            model,
+            dictionary,
+        }
+    }
+
+    /// The language choice scores are made under.
+    pub fn language(&self) -> LanguageChoice {
+        self.language
+    }
+
+    fn dictionary(&self) -> &Dictionary {
+        self.dictionary.as_ref().unwrap_or_else(|| dictionary())
+    }
+
+    // The trained model's bigrams if it has them, else English.
+    fn bigram_model(&self) -> &BigramModel {
+        self.bigrams.as_ref().unwrap_or_else(|| BigramModel::english())
+    }
+
+    fn language_model(&self, language: Language) -> &LanguageModel {
+        match &self.model {
+            Some(model) if language == Language::Custom => model,
+            _ => builtin_model(language),
+        }
+    }
+
+    // The language the statistical attacks (trigram and unigram fitness) use.
+    fn primary_language(&self) -> Language {
+        match self.language {
+            LanguageChoice::Fixed(lang) => lang,
+            LanguageChoice::Auto => Language::English,
+        }
+    }
+
+    // Languages tried in auto mode: the built-in ones plus a loaded model.
+    fn auto_languages(&self) -> Vec<Language> {
+        let mut languages = LANGUAGES.to_vec();
+        if self.model.is_some() {
+            languages.push(Language::Custom);
+        }
+        languages
+    }
+
+    // Log-likelihood of a letter buffer (values 0-25) under the primary
+    // language's trigram model.
+    fn trigram_score(&self, letters: &[u8]) -> f64 {
+        self.language_trigram_score(letters, self.primary_language())
+    }
+
+    fn language_trigram_score(&self, letters: &[u8], language: Language) -> f64 {
+        let model = &self.language_model(language).trigrams;
+        letters
+            .windows(3)
+            .map(|w| model[w[0] as usize * 676 + w[1] as usize * 26 + w[2] as usize])
+            .sum()
+    }
+
+    /// score_english's recipe with another language's letters and words.
+    pub fn score_language(&self, text: &str, language: Language) -> i32 {
+        if language == Language::English {
+            return english_score(text.as_bytes(), self.dictionary());
+        }
+        score_with_model(text, self.language_model(language))
+    }
+
+    // `score` for a buffer that is known to hold UTF-8; English is scored
+    // in place, other languages go through a string.
+    fn score_bytes(&self, text: &[u8]) -> i32 {
+        match self.language {
+            LanguageChoice::Fixed(Language::English) => english_score(text, self.dictionary()),
+            _ => self.score(std::str::from_utf8(text).unwrap()),
+        }
+    }
+
+    /// Score under the chosen language; in auto mode, under the language
+    /// the text is detected as.
+    pub fn score(&self, text: &str) -> i32 {
+        self.score_language(text, self.detect_language(text))
+    }
+
+    /// The fixed choice, or in auto mode the language with the most words
+    /// of the text among its DETECTION_WORDS most common, ties (such as
+    /// unspaced text) going to the best trigram log-likelihood. The scores
+    /// themselves can't decide this: their word lists differ in size, so the
+    /// largest list would win on text in any language.
+    pub fn detect_language(&self, text: &str) -> Language {
+        match self.language {
+            LanguageChoice::Fixed(lang) => lang,
+            LanguageChoice::Auto => {
+                let text = fold_accents(text).to_ascii_lowercase();
+                let words: Vec<&str> = text.split(|c: char| !c.is_ascii_alphabetic()).filter(|w| !w.is_empty()).collect();
+                let letters = letter_values(&text);
+                let fit = |lang: Language| {
+                    let model = self.language_model(lang);
+                    let hits = words.iter().filter(|w| model.top_words.contains(**w)).count();
+                    (hits, self.language_trigram_score(&letters, lang))
+                };
+                self.auto_languages()
+                    .into_iter()
+                    .map(|lang| (fit(lang), lang))
+                    .max_by(|(a, _), (b, _)| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)))
+                    .map_or(Language::English, |(_, lang)| lang)
+            }
+        }
+    }
+
+    // segment_words with the extra words in the dictionary.
+    fn segment_words(&self, text: &str) -> String {
+        segment_with(text, self.dictionary())
+    }
+}
+
+fn score_with_model(text: &str, model: &LanguageModel) -> i32 {
+    let text = fold_accents(text).to_ascii_lowercase();
+    let mut score = text.chars().filter(|&c| model.frequent.contains(c)).count() as i32;
+
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .filter(|w| w.len() >= 3)
+        .collect();
+    let valid = words.iter().filter(|w| model.words.contains(**w)).count() as i32;
+    score += valid * 10;
+    if !words.is_empty() {
+        score += (valid * 100) / words.len() as i32 * 2;
+    }
+
+    // Trained models: share of quadgrams seen in training, which still
+    // works when the text has no word breaks.
+    if let Some(quadgrams) = &model.quadgrams {
+        let letters = letter_values(&text);
+        let (known, total) = letters.windows(4).fold((0, 0), |(known, total), w| {
+            let idx = ((w[0] as usize * 26 + w[1] as usize) * 26 + w[2] as usize) * 26 + w[3] as usize;
+            (known + (quadgrams[idx] > model.quadgram_floor) as i32, total + 1)
+        });
+        if total > 0 {
+            score += known * 100 / total;
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod language_tests {
+    use super::*;
+
+    const ENGLISH: &str = "The old man walked slowly along the river, thinking about the letters his brother had written.";
+    const FRENCH: &str = "Le vieil homme marchait lentement le long de la rivière en pensant aux lettres que son frère avait écrites.";
+    const GERMAN: &str = "Der alte Mann ging langsam am Fluss entlang und dachte an die Briefe, die sein Bruder geschrieben hatte.";
+
+    #[test]
+    fn auto_mode_detects_each_language() {
+        let scorer = Scorer::new(LanguageChoice::Auto, None, &[]);
+        assert_eq!(scorer.detect_language(ENGLISH), Language::English);
+        assert_eq!(scorer.detect_language(FRENCH), Language::French);
+        assert_eq!(scorer.detect_language(GERMAN), Language::German);
+    }
+
+    #[test]
+    fn auto_mode_scores_under_the_detected_language() {
+        let scorer = Scorer::new(LanguageChoice::Auto, None, &[]);
+        assert_eq!(scorer.score(FRENCH), scorer.score_language(FRENCH, Language::French));
+        assert_eq!(scorer.score(GERMAN), scorer.score_language(GERMAN, Language::German));
+        // The German text, Caesar-shifted, is no longer German.
+        assert!(scorer.score(&decrypt_caesar(GERMAN, 7)) < scorer.score(GERMAN));
+    }
+
+    #[test]
+    fn fixed_choice_is_not_second_guessed() {
+        let scorer = Scorer::new(LanguageChoice::Fixed(Language::English), None, &[]);
+        assert_eq!(scorer.detect_language(FRENCH), Language::English);
+        assert_eq!(scorer.score(FRENCH), scorer.score_language(FRENCH, Language::English));
+    }
+}
+
+// ========== CONFIDENCE ==========
+
+const DEFAULT_SUCCESS_CONFIDENCE: u8 = 70;
+// Fitness z-scores (against random letters) below the first count for
+// nothing; at the second the statistics are taken as conclusive.
+const CONFIDENCE_Z_MIN: f64 = 3.0;
+const CONFIDENCE_Z_FULL: f64 = 8.0;
+// A lead over the runner-up of this fraction of the random-to-language
+// fitness gap counts as a clear win.
+const CONFIDENCE_CLEAR_MARGIN: f64 = 0.25;
+const CONFIDENCE_FITNESS_WEIGHT: f64 = 0.5;
+const CONFIDENCE_COVERAGE_WEIGHT: f64 = 0.3;
+const CONFIDENCE_MARGIN_WEIGHT: f64 = 0.2;
+
+/// How sure [`assess_results`] is that a result is the real plaintext.
+pub struct Confidence {
+    /// 0-100.
+    pub percent: u8,
+    /// Language the plaintext was judged in.
+    pub language: Language,
+    /// Trigram fitness above random letters, in standard deviations.
+    pub z_score: f64,
+    /// Share of words (0-1) found in the dictionary.
+    pub coverage: f64,
+    /// Fitness lead (0-1) over the best other result.
+    pub margin: f64,
+}
+
+// Mean and spread of one trigram's log-probability, for uniformly random
+// letters and for text drawn from the model itself.
+struct FitnessBaseline {
+    random_mean: f64,
+    random_sd: f64,
+    language_mean: f64,
+}
+
+impl FitnessBaseline {
+    fn of(model: &LanguageModel) -> Self {
+        let n = model.trigrams.len() as f64;
+        let random_mean = model.trigrams.iter().sum::<f64>() / n;
+        let random_var = model.trigrams.iter().map(|l| (l - random_mean).powi(2)).sum::<f64>() / n;
+        let total: f64 = model.trigrams.iter().map(|l| l.exp()).sum();
+        let language_mean = model.trigrams.iter().map(|l| l.exp() / total * l).sum();
+        FitnessBaseline { random_mean, random_sd: random_var.sqrt(), language_mean }
+    }
+}
+
+// Mean trigram log-probability within runs of letters, and how many
+// trigrams it is over.
+fn trigram_fitness(text: &str, model: &LanguageModel) -> (f64, usize) {
+    let text = fold_accents(text);
+    let (mut sum, mut count) = (0.0, 0);
+    for run in text.split(|c: char| !c.is_ascii_alphabetic()) {
+        let letters = letter_values(run);
+        for w in letters.windows(3) {
+            sum += model.trigrams[w[0] as usize * 676 + w[1] as usize * 26 + w[2] as usize];
+            count += 1;
+        }
+    }
+    if count == 0 { (f64::NEG_INFINITY, 0) } else { (sum / count as f64, count) }
+}
+
+// Share of words found in the language's dictionary (the full English
+// dictionary, otherwise the model's word list).
+fn word_coverage(scorer: &Scorer, text: &str, language: Language) -> f64 {
+    if language == Language::English {
+        return valid_word_percent(text.as_bytes(), scorer.dictionary()) as f64 / 100.0;
+    }
+    let text = fold_accents(text).to_ascii_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_ascii_alphabetic()).filter(|w| !w.is_empty()).collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+    let words_set = &scorer.language_model(language).words;
+    words.iter().filter(|w| words_set.contains(**w)).count() as f64 / words.len() as f64
+}
+
+/// Confidence for each result (given best first) that it is the real
+/// plaintext. Combines how far its trigram fitness sits above random text
+/// (scaled by how significant that is for its length), dictionary coverage
+/// and its fitness lead over the best of the other results.
+pub fn assess_results(scorer: &Scorer, results: &[CrackResult]) -> Vec<Confidence> {
+    let fitness: Vec<(Language, f64, usize)> = results
+        .iter()
+        .map(|r| {
+            let language = scorer.detect_language(&r.plaintext_full);
+            let (mean, count) = trigram_fitness(&r.plaintext_full, scorer.language_model(language));
+            (language, mean, count)
+        })
+        .collect();
+
+    fitness
+        .iter()
+        .enumerate()
+        .map(|(i, &(language, mean, count))| {
+            let base = FitnessBaseline::of(scorer.language_model(language));
+            let gap = base.language_mean - base.random_mean;
+            let (z_score, fit) = if count == 0 {
+                (0.0, 0.0)
+            } else {
+                let z = (mean - base.random_mean) / (base.random_sd / (count as f64).sqrt());
+                (z, ((mean - base.random_mean) / gap).clamp(0.0, 1.0))
+            };
+            let significance = ((z_score - CONFIDENCE_Z_MIN) / (CONFIDENCE_Z_FULL - CONFIDENCE_Z_MIN)).clamp(0.0, 1.0);
+
+            let runner_up = fitness
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &(_, other, _))| other)
+                .fold(f64::NEG_INFINITY, f64::max);
+            let margin = if count == 0 {
+                0.0
+            } else if runner_up == f64::NEG_INFINITY {
+                1.0
+            } else {
+                ((mean - runner_up) / (gap * CONFIDENCE_CLEAR_MARGIN)).clamp(0.0, 1.0)
+            };
+            let coverage = word_coverage(scorer, &results[i].plaintext_full, language);
+
+            let confidence = CONFIDENCE_FITNESS_WEIGHT * fit * significance
+                + CONFIDENCE_COVERAGE_WEIGHT * coverage
+                + CONFIDENCE_MARGIN_WEIGHT * margin;
+            Confidence {
+                percent: (confidence * 100.0).round() as u8,
+                language,
+                z_score,
+                coverage,
+                margin,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod confidence_tests {
+    use super::*;
+
+    const PLAIN: &str = "The ancient library stood on the hillside, its weathered stone walls holding \
+        countless secrets within. Scholars would travel for months to study its rare manuscripts.";
+
+    fn results(candidates: &[&str]) -> Vec<CrackResult> {
+        let mut top_n = TopN::new(candidates.len());
+        for (i, plain) in candidates.iter().enumerate() {
+            top_n.insert_lightweight(100 - i as i32, "Caesar".to_string(), format!("shift {}", i + 1), plain);
+        }
+        top_n.sorted_results()
+    }
+
+    fn random_text(seed: u64, len: usize) -> String {
+        let mut rng = Rng::new(seed);
+        (0..len).map(|i| if i % 6 == 5 { ' ' } else { (b'a' + rng.below(26) as u8) as char }).collect()
+    }
+
+    #[test]
+    fn correct_english_clears_the_threshold() {
+        let scorer = Scorer::default();
+        let wrong = decrypt_caesar(PLAIN, 9);
+        let assessed = assess_results(&scorer, &results(&[PLAIN, &wrong]));
+        assert!(assessed[0].percent >= DEFAULT_SUCCESS_CONFIDENCE, "{}", assessed[0].percent);
+        assert_eq!(assessed[0].language, Language::English);
+        assert!(assessed[0].coverage > 0.8);
+        assert!(assessed[1].percent < DEFAULT_SUCCESS_CONFIDENCE);
+    }
+
+    #[test]
+    fn random_text_does_not() {
+        let scorer = Scorer::default();
+        let (first, second) = (random_text(1, PLAIN.len()), random_text(2, PLAIN.len()));
+        for confidence in assess_results(&scorer, &results(&[&first, &second])) {
+            assert!(confidence.percent < DEFAULT_SUCCESS_CONFIDENCE, "{}", confidence.percent);
+            assert!(confidence.z_score < CONFIDENCE_Z_MIN);
+        }
+    }
+}
+
+// ========== TRAINED MODELS ==========
+
+// File layout (little endian): magic, version, then for n = 1..=4 the floor
+// log-probability, the entry count and each seen n-gram as a varint index
+// delta plus an f32 log-probability; finally the word list, most frequent
+// first, as length-prefixed bytes.
+const MODEL_MAGIC: &[u8; 4] = b"VBLM";
+const MODEL_VERSION: u8 = 1;
+/// How many words a trained model keeps by default.
+pub const DEFAULT_TRAINED_WORDS: usize = 5000;
+
+/// N-gram counts (n = 1..=4, within runs of letters) and word counts for a
+/// body of text. Accents are folded so "été" counts as "ete".
+pub struct TrainingCounts {
+    /// Counts for n = 1..=4, indexed by the letters as a base-26 number.
+    pub ngrams: [Vec<u64>; 4],
+    pub words: HashMap<String, u64>,
+}
+
+impl Default for TrainingCounts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TrainingCounts {
+    pub fn new() -> Self {
+        TrainingCounts {
+            ngrams: std::array::from_fn(|n| vec![0u64; 26usize.pow(n as u32 + 1)]),
+            words: HashMap::new(),
+        }
+    }
+
+    pub fn add_text(&mut self, text: &str) {
+        let text = fold_accents(text).to_ascii_lowercase();
+        for word in text.split(|c: char| !c.is_ascii_lowercase()).filter(|w| !w.is_empty()) {
+            let letters: Vec<usize> = word.bytes().map(|b| (b - b'a') as usize).collect();
+            for (n, table) in self.ngrams.iter_mut().enumerate() {
+                for gram in letters.windows(n + 1) {
+                    table[gram.iter().fold(0, |idx, &l| idx * 26 + l)] += 1;
+                }
+            }
+            *self.words.entry(word.to_string()).or_insert(0) += 1;
+        }
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// The model file for `counts`, keeping the `word_limit` most frequent words.
+pub fn encode_model(counts: &TrainingCounts, word_limit: usize) -> Vec<u8> {
+    let mut out = MODEL_MAGIC.to_vec();
+    out.push(MODEL_VERSION);
+
+    for table in &counts.ngrams {
+        // Unseen n-grams get half a count, as with the built-in tables.
+        let total = table.iter().sum::<u64>() as f64 + 0.5;
+        let seen: Vec<(usize, u64)> = table.iter().copied().enumerate().filter(|&(_, c)| c > 0).collect();
+        out.extend_from_slice(&((0.5 / total).ln() as f32).to_le_bytes());
+        out.extend_from_slice(&(seen.len() as u32).to_le_bytes());
+        let mut previous = 0;
+        for (idx, count) in seen {
+            write_varint(&mut out, (idx - previous) as u32);
+            out.extend_from_slice(&((count as f64 / total).ln() as f32).to_le_bytes());
+            previous = idx;
+        }
+    }
+
+    let mut words: Vec<(&String, &u64)> = counts.words.iter().filter(|(w, _)| w.len() <= 255).collect();
+    words.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    words.truncate(word_limit);
+    out.extend_from_slice(&(words.len() as u32).to_le_bytes());
+    for (word, _) in words {
+        out.push(word.len() as u8);
+        out.extend_from_slice(word.as_bytes());
+    }
+    out
+}
+
+// Reads the format written by encode_model.
+fn decode_model(bytes: &[u8]) -> std::result::Result<LanguageModel, String> {
+    let mut pos = 0;
+    let mut take = |len: usize| -> std::result::Result<&[u8], String> {
+        let slice = bytes.get(pos..pos + len).ok_or("model file is truncated")?;
+        pos += len;
+        Ok(slice)
+    };
+
+    if take(4)? != MODEL_MAGIC {
+        return Err("not a trained model file".to_string());
+    }
+    let version = take(1)?[0];
+    if version != MODEL_VERSION {
+        return Err(format!("unsupported model version {}", version));
+    }
+
+    let mut tables: Vec<(Vec<f64>, f64)> = Vec::with_capacity(4);
+    for n in 1..=4u32 {
+        let size = 26usize.pow(n);
+        let floor = f32::from_le_bytes(take(4)?.try_into().unwrap_or_default()) as f64;
+        let entries = u32::from_le_bytes(take(4)?.try_into().unwrap_or_default());
+        let mut table = vec![floor; size];
+        let mut idx = 0usize;
+        for _ in 0..entries {
+            let mut delta = 0u32;
+            let mut shift = 0;
+            loop {
+                let byte = take(1)?[0];
+                delta |= ((byte & 0x7f) as u32) << shift;
+                shift += 7;
+                if byte & 0x80 == 0 || shift > 28 {
+                    break;
+                }
+            }
+            idx += delta as usize;
+            let log_prob = f32::from_le_bytes(take(4)?.try_into().unwrap_or_default());
+            *table.get_mut(idx).ok_or("n-gram index out of range")? = log_prob as f64;
+        }
+        tables.push((table, floor));
+    }
+
+    // Words are stored most frequent first.
+    let count = u32::from_le_bytes(take(4)?.try_into().unwrap_or_default());
+    let mut words = std::collections::HashSet::new();
+    let mut top_words = std::collections::HashSet::new();
+    for i in 0..count {
+        let len = take(1)?[0] as usize;
+        let word = std::str::from_utf8(take(len)?).map_err(|_| "word list is not UTF-8")?;
+        if (i as usize) < DETECTION_WORDS {
+            top_words.insert(word.to_string());
+        }
+        words.insert(word.to_string());
+    }
+
+    let (quadgrams, quadgram_floor) = tables.pop().unwrap_or_default();
+    let (trigrams, _) = tables.pop().unwrap_or_default();
+    let (bigrams, _) = tables.pop().unwrap_or_default();
+    let (monograms, _) = tables.pop().unwrap_or_default();
+
+    let mut unigrams = [0.0; 26];
+    unigrams.copy_from_slice(&monograms);
+    let mut order: Vec<usize> = (0..26).collect();
+    order.sort_by(|&a, &b| unigrams[b].total_cmp(&unigrams[a]));
+    let frequent = order.iter().take(12).map(|&i| (b'a' + i as u8) as char).collect();
+
+    Ok(LanguageModel {
+        unigrams,
+        bigrams: Some(bigrams),
+        trigrams,
+        quadgrams: Some(quadgrams),
+        quadgram_floor,
+        frequent,
+        words,
+        top_words,
+    })
+}
+
+/// Reads a model file written from [`encode_model`].
+pub fn load_model(path: &str) -> std::result::Result<LanguageModel, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("could not read {}: {}", path, e))?;
+    decode_model(&bytes)
+}
+
+#[cfg(test)]
+mod model_tests {
+    use super::*;
+
+    fn counts(text: &str) -> TrainingCounts {
+        let mut counts = TrainingCounts::new();
+        counts.add_text(text);
+        counts
+    }
+
+    #[test]
+    fn saved_model_loads_back() {
+        let counts = counts("Le chat noir dort. Le chien noir mange, le chat dort encore à côté.");
+        let model = decode_model(&encode_model(&counts, 3)).unwrap();
+
+        let total = counts.ngrams[0].iter().sum::<u64>() as f64 + 0.5;
+        for (letter, &count) in counts.ngrams[0].iter().enumerate() {
+            let expected = if count > 0 { count as f64 / total } else { 0.5 / total }.ln();
+            assert!((model.unigrams[letter] - expected).abs() < 1e-5, "letter {}", letter);
+        }
+        let quadgrams = model.quadgrams.as_ref().unwrap();
+        let chat = [2, 7, 0, 19].iter().fold(0, |idx, &l| idx * 26 + l);
+        assert!(quadgrams[chat] > model.quadgram_floor);
+        assert_eq!(quadgrams[0], model.quadgram_floor);
+
+        // Most frequent first, ties alphabetical; "côté" is stored folded.
+        let expected: std::collections::HashSet<String> = ["le", "chat", "dort"].map(String::from).into();
+        assert_eq!(model.words, expected);
+        assert!(decode_model(&encode_model(&counts, 100)).unwrap().words.contains("cote"));
+    }
+
+    #[test]
+    fn rejects_damaged_files() {
+        let bytes = encode_model(&counts("some training text"), 10);
+        assert!(decode_model(&bytes[..bytes.len() - 1]).is_err());
+        assert!(decode_model(b"VBLM").is_err());
+
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        assert!(decode_model(&wrong_magic).is_err());
+        let mut wrong_version = bytes;
+        wrong_version[4] = MODEL_VERSION + 1;
+        assert!(decode_model(&wrong_version).is_err());
+    }
+}
+
+// ========== SEARCH CONFIGURATION ==========
+
+/// Section names used in the config file and by `--set`, by menu number.
+pub const CIPHER_NAMES: [&str; CIPHER_COUNT] = [
+    "caesar", "rot13", "atbash", "vigenere", "rail_fence", "affine", "beaufort",
+    "columnar", "playfair", "polybius", "bacon", "reverse", "hybrid", "xor",
+    "pipeline", "running_key", "enigma", "homophonic", "checkerboard", "stream",
+    "keyword",
+];
+
+// Longest key a sweep may be configured for; 26^12 still fits a u64 count.
+const MAX_SWEEP_KEY_LEN: usize = 12;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Scheduler {
+    // Most promising families first, cheap ones before expensive ones.
+    Promising,
+    // Menu order.
+    Menu,
+}
+
+/// Search ranges, time budgets and the success threshold: built-in
+/// defaults, changed by a config file or `section.key=value` settings.
+pub struct SearchConfig {
+    vigenere_keys: std::ops::RangeInclusive<usize>,
+    beaufort_keys: std::ops::RangeInclusive<usize>,
+    hybrid_keys: std::ops::RangeInclusive<usize>,
+    rails: std::ops::RangeInclusive<usize>,
+    columns: std::ops::RangeInclusive<usize>,
+    playfair_keys: Vec<String>,
+    // Wall-clock limit for a whole run, and per cipher by menu number.
+    budget: Option<std::time::Duration>,
+    cipher_budgets: HashMap<usize, std::time::Duration>,
+    scheduler: Scheduler,
+    // Confidence (percent) the best result needs to be reported as solved.
+    success_confidence: u8,
+    // Seed for the randomized attacks; taken from the clock when unset.
+    seed: Option<u64>,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig {
+            vigenere_keys: 1..=5,
+            beaufort_keys: 1..=5,
+            hybrid_keys: 1..=4,
+            rails: 2..=15,
+            columns: 2..=10,
+            playfair_keys: ["key", "secret", "cipher", "enigma", "cryptography", "library", "ancient", "knowledge"]
+                .iter()
+                .map(|k| k.to_string())
+                .collect(),
+            budget: None,
+            cipher_budgets: HashMap::new(),
+            scheduler: Scheduler::Promising,
+            success_confidence: DEFAULT_SUCCESS_CONFIDENCE,
+            seed: None,
+        }
+    }
+}
+
+// A value from the small TOML subset the config file uses.
+enum ConfigValue {
+    Number(f64),
+    Text(String),
+    List(Vec<ConfigValue>),
+}
+
+impl ConfigValue {
+    fn parse(text: &str) -> std::result::Result<Self, String> {
+        let text = text.trim();
+        if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+            return inner
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(ConfigValue::parse)
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map(ConfigValue::List);
+        }
+        if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+            return Ok(ConfigValue::Text(inner.to_string()));
+        }
+        text.parse().map(ConfigValue::Number).map_err(|_| format!("can't read value '{}'", text))
+    }
+
+    fn number(&self) -> std::result::Result<f64, String> {
+        match self {
+            ConfigValue::Number(n) if *n >= 0.0 => Ok(*n),
+            _ => Err("expected a non-negative number".to_string()),
+        }
+    }
+
+    fn text(&self) -> std::result::Result<&str, String> {
+        match self {
+            ConfigValue::Text(t) => Ok(t),
+            _ => Err("expected a quoted string".to_string()),
+        }
+    }
+
+    // `[min, max]`, or a single number for both ends.
+    fn range(&self, min_allowed: usize, max_allowed: usize) -> std::result::Result<std::ops::RangeInclusive<usize>, String> {
+        let ends = match self {
+            ConfigValue::List(items) if items.len() == 2 => (items[0].number()?, items[1].number()?),
+            ConfigValue::Number(n) => (*n, *n),
+            _ => return Err("expected [min, max]".to_string()),
+        };
+        let (lo, hi) = (ends.0 as usize, ends.1 as usize);
+        if lo < min_allowed || hi > max_allowed || lo > hi {
+            return Err(format!("range must be within {}..={} and ascending", min_allowed, max_allowed));
+        }
+        Ok(lo..=hi)
+    }
+}
+
+impl SearchConfig {
+    // Applies one `key = value` from section `section` ("" for top level).
+    fn set(&mut self, section: &str, key: &str, value: &ConfigValue) -> std::result::Result<(), String> {
+        let secs = |v: &ConfigValue| v.number().map(std::time::Duration::from_secs_f64);
+        match (section, key) {
+            ("", "budget") => self.budget = Some(secs(value)?),
+            ("", "success_confidence") => self.success_confidence = value.number()?.min(100.0) as u8,
+            ("", "seed") => self.seed = Some(value.number()? as u64),
+            ("", "scheduler") => {
+                self.scheduler = match value.text()? {
+                    "promising" => Scheduler::Promising,
+                    "menu" => Scheduler::Menu,
+                    other => return Err(format!("unknown scheduler '{}' (expected promising or menu)", other)),
+                }
+            }
+            ("vigenere", "key_lengths") => self.vigenere_keys = value.range(1, MAX_SWEEP_KEY_LEN)?,
+            ("beaufort", "key_lengths") => self.beaufort_keys = value.range(1, MAX_SWEEP_KEY_LEN)?,
+            ("hybrid", "key_lengths") => self.hybrid_keys = value.range(1, MAX_SWEEP_KEY_LEN)?,
+            ("rail_fence", "rails") => self.rails = value.range(2, 1000)?,
+            ("columnar", "columns") => self.columns = value.range(2, 26)?,
+            ("playfair", "keys") => {
+                let ConfigValue::List(items) = value else {
+                    return Err("expected a list of strings".to_string());
+                };
+                self.playfair_keys = items.iter().map(|k| k.text().map(str::to_string)).collect::<std::result::Result<_, _>>()?;
+            }
+            (name, "budget") => match CIPHER_NAMES.iter().position(|&n| n == name) {
+                Some(i) => {
+                    self.cipher_budgets.insert(i + 1, secs(value)?);
+                }
+                None => return Err(format!("unknown cipher '{}'", name)),
+            },
+            _ => return Err(format!("unknown setting '{}'", if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) })),
+        }
+        Ok(())
+    }
+
+    /// Applies `section.key=value` or `key=value`, as given to `--set`.
+    pub fn set_from_arg(&mut self, arg: &str) -> std::result::Result<(), String> {
+        let (path, value) = arg.split_once('=').ok_or_else(|| format!("expected key=value, got '{}'", arg))?;
+        let (section, key) = path.trim().rsplit_once('.').unwrap_or(("", path.trim()));
+        self.set(section, key, &ConfigValue::parse(value)?)
+    }
+
+    /// Applies a config file: `key = value` lines under `[section]` headers.
+    pub fn load(&mut self, path: &str) -> std::result::Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let mut section = String::new();
+        for (n, line) in text.lines().enumerate() {
+            let err = |e: String| format!("{} line {}: {}", path, n + 1, e);
+            // Comments run from a # outside quotes to the end of the line.
+            let mut quoted = false;
+            let end = line
+                .char_indices()
+                .find(|&(_, c)| {
+                    quoted ^= c == '"';
+                    c == '#' && !quoted
+                })
+                .map_or(line.len(), |(i, _)| i);
+            let line = line[..end].trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| err("expected key = value".to_string()))?;
+            let value = ConfigValue::parse(value).map_err(err)?;
+            self.set(&section, key.trim(), &value).map_err(err)?;
+        }
+        Ok(())
+    }
+
+    /// Time budget for one cipher by menu number: its own, else the run's.
+    pub fn budget_for(&self, cipher: usize) -> Option<std::time::Duration> {
+        self.cipher_budgets.get(&cipher).copied().or(self.budget)
+    }
+
+    /// Confidence (percent) the best result needs to count as solved.
+    pub fn success_confidence(&self) -> u8 {
+        self.success_confidence
+    }
+
+    /// Seed for the randomized attacks: the configured one, else one taken
+    /// from the clock.
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_else(time_seed)
+    }
+}
+
+fn describe_range(range: &std::ops::RangeInclusive<usize>) -> String {
+    if range.start() == range.end() {
+        range.start().to_string()
+    } else {
+        format!("{}-{}", range.start(), range.end())
+    }
+}
+
+// ========== SESSIONS & PROGRESS ==========
+
+/// A status update from a running search, for the front end to show.
+pub enum Status {
+    /// A line of narration, such as "Testing ROT13...".
+    Message(String),
+    /// How far a key-space sweep has got; sent about twice a second.
+    Progress {
+        tried: u64,
+        total: u64,
+        /// Keys per second.
+        rate: f64,
+        /// Estimated seconds left.
+        eta: f64,
+        /// Score and preview of the best candidate so far.
+        best: Option<(i32, String)>,
+    },
+    /// The sweep the progress updates were for has ended.
+    ProgressDone,
+}
+
+// Checkpointing for sweeps: the file to write, the menu choice being run (0
+// for all ciphers), recorded so a resumed run repeats it, and the checkpoint
+// to resume from until the sweep it belongs to takes it.
+struct CheckpointState {
+    path: std::path::PathBuf,
+    menu_choice: usize,
+    resume: std::cell::RefCell<Option<Checkpoint>>,
+}
+
+/// What a search needs besides the ciphertext: the scorer, the search
+/// settings, when to stop, where status updates go and whether sweeps keep
+/// a checkpoint file. A session runs one search at a time.
+pub struct Session<'a> {
+    scorer: &'a Scorer,
+    config: &'a SearchConfig,
+    stop: Option<&'a std::sync::atomic::AtomicBool>,
+    report: Option<&'a dyn Fn(Status)>,
+    // End of the time budget for the cipher being run, if it has one.
+    deadline: std::cell::Cell<Option<std::time::Instant>>,
+    checkpoints: Option<CheckpointState>,
+}
+
+impl<'a> Session<'a> {
+    /// A session that reports nothing, runs until done and writes no
+    /// checkpoints.
+    pub fn new(scorer: &'a Scorer, config: &'a SearchConfig) -> Self {
+        Session { scorer, config, stop: None, report: None, deadline: std::cell::Cell::new(None), checkpoints: None }
+    }
+
+    /// Long searches stop early once `flag` is set (from a Ctrl-C handler,
+    /// say), keeping the results found so far.
+    pub fn with_stop_flag(mut self, flag: &'a std::sync::atomic::AtomicBool) -> Self {
+        self.stop = Some(flag);
+        self
+    }
+
+    /// Sends status lines and sweep progress to `report`.
+    pub fn with_reporter(mut self, report: &'a dyn Fn(Status)) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    /// Has key-space sweeps save their position and results to `path` every
+    /// so often and when stopped, recording `menu_choice` (0 for all
+    /// ciphers). A sweep that matches `resume` starts from it.
+    pub fn with_checkpoint(mut self, path: impl Into<std::path::PathBuf>, menu_choice: usize, resume: Option<Checkpoint>) -> Self {
+        self.checkpoints = Some(CheckpointState { path: path.into(), menu_choice, resume: std::cell::RefCell::new(resume) });
+        self
+    }
+
+    pub fn scorer(&self) -> &'a Scorer {
+        self.scorer
+    }
+
+    pub fn config(&self) -> &'a SearchConfig {
+        self.config
+    }
+
+    /// Whether the stop flag has been set.
+    pub fn interrupted(&self) -> bool {
+        self.stop.is_some_and(|flag| flag.load(std::sync::atomic::Ordering::Relaxed))
+    }
+
+    fn out_of_time(&self) -> bool {
+        self.deadline.get().is_some_and(|d| std::time::Instant::now() >= d)
+    }
+
+    // Polled by long searches: the stop flag or an exhausted time budget.
+    fn should_stop(&self) -> bool {
+        self.interrupted() || self.out_of_time()
+    }
+
+    fn status(&self, message: impl Into<String>) {
+        if let Some(report) = self.report {
+            report(Status::Message(message.into()));
+        }
+    }
+}
+
+// How many keys between clock checks, and how often progress is reported.
+const PROGRESS_CHECK_EVERY: u64 = 4096;
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+// Progress of a key-space sweep: keys tried, rate, ETA and the best
+// candidate so far, reported as Status::Progress.
+struct Progress {
+    total: u64,
+    tried: u64,
+    started: std::time::Instant,
+    last_report: std::time::Instant,
+}
+
+impl Progress {
+    fn new(total: u64) -> Self {
+        let now = std::time::Instant::now();
+        Progress { total, tried: 0, started: now, last_report: now }
+    }
+
+    fn tick(&mut self, session: &Session, top_n: &TopN) {
+        self.tried += 1;
+        if session.report.is_some() && self.tried.is_multiple_of(PROGRESS_CHECK_EVERY) && self.last_report.elapsed() >= PROGRESS_INTERVAL {
+            self.last_report = std::time::Instant::now();
+            self.report(session, top_n);
+        }
+    }
+
+    fn report(&self, session: &Session, top_n: &TopN) {
+        let Some(report) = session.report else {
+            return;
+        };
+        let secs = self.started.elapsed().as_secs_f64().max(1e-9);
+        let rate = self.tried as f64 / secs;
+        report(Status::Progress {
+            tried: self.tried,
+            total: self.total,
+            rate,
+            eta: self.total.saturating_sub(self.tried) as f64 / rate.max(1.0),
+            best: top_n.best_result().map(|r| (r.score, r.plaintext_preview.clone())),
+        });
+    }
+
+    fn finish(&self, session: &Session) {
+        if let Some(report) = session.report {
+            report(Status::ProgressDone);
+        }
+        if session.should_stop() {
+            session.status(format!("  ⚠️  Stopped after {} of {} keys.", self.tried, self.total));
+        }
+    }
+}
+
+/// Seconds as `42s`, `3m05s` or `1h02m`.
+pub fn format_duration(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[cfg(test)]
+mod session_tests {
+    use super::*;
+
+    #[test]
+    fn extra_words_only_count_for_their_scorer() {
+        let scorer = Scorer::new(LanguageChoice::Fixed(Language::English), None, &["zorblax".to_string()]);
+        assert!(scorer.score("zorblax") > Scorer::default().score("zorblax"));
+    }
+
+    #[test]
+    fn status_goes_to_the_reporter() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let lines = std::cell::RefCell::new(Vec::new());
+        let report = |status: Status| {
+            if let Status::Message(line) = status {
+                lines.borrow_mut().push(line);
+            }
+        };
+        let session = Session::new(&scorer, &config).with_reporter(&report);
+        crack_specific_cipher(&session, 2, "Uryyb", &mut TopN::new(5));
+        assert!(lines.borrow().iter().any(|l| l == "Testing ROT13..."));
+    }
+
+    #[test]
+    fn stop_flag_ends_a_sweep() {
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop);
+        let mut top_n = TopN::new(5);
+        crack_specific_cipher(&session, 4, "Rijvs", &mut top_n);
+        assert!(top_n.best_result().is_none());
+    }
+}
+
+// ========== CHECKPOINTS ==========
+
+const CHECKPOINT_HEADER: &str = "vigenere_bruteforce checkpoint 1";
+const CHECKPOINT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Where a key-space sweep stopped, with the results it had found.
+// The next key to try is number `index` (in odometer order) among keys of
+// `key_len` letters.
+pub struct Checkpoint {
+    menu_choice: usize,
+    cipher: usize,
+    ciphertext: String,
+    key_len: usize,
+    index: u64,
+    results: Vec<CrackResult>,
+}
+
+impl Checkpoint {
+    /// The menu choice that was being run (0 for all ciphers).
+    pub fn menu_choice(&self) -> usize {
+        self.menu_choice
+    }
+
+    // One record per line, tab-separated, with tabs, newlines and
+    // backslashes escaped.
+    fn encode(&self) -> String {
+        let mut out = format!(
+            "{}\nchoice\t{}\t{}\nciphertext\t{}\nposition\t{}\t{}\n",
+            CHECKPOINT_HEADER,
+            self.menu_choice,
+            self.cipher,
+            escape_field(&self.ciphertext),
+            self.key_len,
+            self.index
+        );
+        for r in &self.results {
+            out.push_str(&format!(
+                "result\t{}\t{}\t{}\t{}",
+                r.score,
+                escape_field(&r.cipher_type),
+                escape_field(&r.params),
+                escape_field(&r.plaintext_full)
+            ));
+            for label in &r.equivalents {
+                out.push('\t');
+                out.push_str(&escape_field(label));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn decode(text: &str) -> std::result::Result<Self, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(CHECKPOINT_HEADER) {
+            return Err("not a checkpoint file".to_string());
+        }
+        let mut checkpoint = Checkpoint {
+            menu_choice: 0,
+            cipher: 0,
+            ciphertext: String::new(),
+            key_len: 0,
+            index: 0,
+            results: Vec::new(),
+        };
+        let bad = |line: &str| format!("bad checkpoint line: {}", line);
+        for line in lines {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields.as_slice() {
+                ["choice", menu, cipher] => {
+                    checkpoint.menu_choice = menu.parse().map_err(|_| bad(line))?;
+                    checkpoint.cipher = cipher.parse().map_err(|_| bad(line))?;
+                }
+                ["ciphertext", text] => checkpoint.ciphertext = unescape_field(text),
+                ["position", len, index] => {
+                    checkpoint.key_len = len.parse().map_err(|_| bad(line))?;
+                    checkpoint.index = index.parse().map_err(|_| bad(line))?;
+                }
+                ["result", score, cipher_type, params, plain, equivalents @ ..] => {
+                    let plaintext_full = unescape_field(plain);
+                    checkpoint.results.push(CrackResult {
+                        score: score.parse().map_err(|_| bad(line))?,
+                        cipher_type: unescape_field(cipher_type),
+                        params: unescape_field(params),
+                        plaintext_preview: plaintext_full.chars().take(80).collect(),
+                        plaintext_full,
+                        equivalents: equivalents.iter().map(|e| unescape_field(e)).collect(),
+                    });
+                }
+                _ => return Err(bad(line)),
+            }
+        }
+        if checkpoint.key_len == 0 {
+            return Err("checkpoint has no search position".to_string());
+        }
+        Ok(checkpoint)
+    }
+
+    // Written to a temporary file first so a crash mid-write keeps the old one.
+    fn save(&self, path: &std::path::Path) -> io::Result<()> {
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, self.encode())?;
+        std::fs::rename(&tmp, path)
+    }
+}
+
+fn escape_field(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape_field(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Reads the checkpoint file left by an interrupted sweep.
+pub fn load_checkpoint(path: &str) -> std::result::Result<Checkpoint, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    Checkpoint::decode(&text)
+}
+
+#[cfg(test)]
+mod checkpoint_tests {
+    use super::*;
+
+    #[test]
+    fn written_checkpoint_parses_back() {
+        let mut top_n = TopN::new(5);
+        top_n.insert_lightweight(120, "Vigenère".to_string(), "key=\"lemon\"".to_string(), "attack\tat\ndawn \\ ok\r");
+        top_n.insert_lightweight(120, "Beaufort".to_string(), "key=abc".to_string(), "attack\tat\ndawn \\ ok\r");
+        top_n.insert_lightweight(-7, "Caesar".to_string(), String::new(), "");
+        let checkpoint = Checkpoint {
+            menu_choice: 0,
+            cipher: 3,
+            ciphertext: "Lxfopv ef\trxp\nbé \\n".to_string(),
+            key_len: 4,
+            index: 123_456_789_012,
+            results: top_n.sorted_results(),
+        };
+
+        let parsed = Checkpoint::decode(&checkpoint.encode()).unwrap();
+        assert_eq!(parsed.menu_choice, checkpoint.menu_choice);
+        assert_eq!(parsed.cipher, checkpoint.cipher);
+        assert_eq!(parsed.ciphertext, checkpoint.ciphertext);
+        assert_eq!((parsed.key_len, parsed.index), (checkpoint.key_len, checkpoint.index));
+        assert!(parsed.results == checkpoint.results);
+        assert_eq!(parsed.results[0].equivalents.len(), 1);
+    }
+
+    #[test]
+    fn rejects_damaged_checkpoints() {
+        assert!(Checkpoint::decode("").is_err());
+        assert!(Checkpoint::decode("something else\nchoice\t0\t3\n").is_err());
+        let header = |rest: &str| format!("{}\n{}", CHECKPOINT_HEADER, rest);
+        assert!(Checkpoint::decode(&header("choice\t0\t3\n")).is_err(), "no position");
+        assert!(Checkpoint::decode(&header("position\tfour\t0\n")).is_err());
+        assert!(Checkpoint::decode(&header("position\t4\t0\nresult\tx\tCaesar\t\t\n")).is_err());
+        assert!(Checkpoint::decode(&header("position\t4\t0\nsurprise\n")).is_err());
+    }
+
+    #[test]
+    fn stopped_sweep_writes_to_the_sessions_path() {
+        let path = std::env::temp_dir().join(format!("sweep_{}.checkpoint", std::process::id()));
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let stop = std::sync::atomic::AtomicBool::new(true);
+        let session = Session::new(&scorer, &config).with_stop_flag(&stop).with_checkpoint(&path, 4, None);
+        crack_specific_cipher(&session, 4, "Rijvs uyvjn", &mut TopN::new(5));
+
+        let checkpoint = load_checkpoint(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((checkpoint.menu_choice, checkpoint.cipher), (4, 4));
+        assert_eq!(checkpoint.ciphertext, "Rijvs uyvjn");
+    }
+}
+
+impl Session<'_> {
+    // Cipher to start an all-ciphers pass from; the ones scheduled before it
+    // were finished before the checkpoint was written.
+    fn resume_first_cipher(&self) -> Option<usize> {
+        self.checkpoints.as_ref()?.resume.borrow().as_ref().map(|c| c.cipher)
+    }
+
+    fn take_checkpoint(&self, cipher: usize, ciphertext: &str) -> Option<Checkpoint> {
+        let mut resume = self.checkpoints.as_ref()?.resume.borrow_mut();
+        if resume.as_ref().is_some_and(|c| c.cipher == cipher && c.ciphertext == ciphertext) {
+            resume.take()
+        } else {
+            None
+        }
+    }
+
+    // True if the checkpoint file was written.
+    fn save_checkpoint(&self, cipher: usize, ciphertext: &str, key_len: usize, index: u64, top_n: &TopN) -> bool {
+        let Some(state) = &self.checkpoints else {
+            return false;
+        };
+        let checkpoint = Checkpoint {
+            menu_choice: state.menu_choice,
+            cipher,
+            ciphertext: ciphertext.to_string(),
+            key_len,
+            index,
+            results: top_n.sorted_results(),
+        };
+        match checkpoint.save(&state.path) {
+            Ok(()) => true,
+            Err(e) => {
+                self.status(format!("Could not write checkpoint {}: {}", state.path.display(), e));
+                false
+            }
+        }
+    }
+
+    // Removes the checkpoint file once the sweep that wrote it has finished.
+    fn remove_checkpoint(&self) {
+        if let Some(state) = &self.checkpoints {
+            let _ = std::fs::remove_file(&state.path);
+        }
+    }
+}
+
+// ========== KEY-SPACE SWEEPS ==========
+
+// The ciphertext with its letters pulled out once, plus a scratch copy that
+// each candidate key overwrites in place. Only the ASCII letter bytes
+// change, so the scratch buffer stays valid UTF-8.
+struct LetterBuffer {
+    scratch: Vec<u8>,
+    // (byte offset, letter value 0-25, case base)
+    letters: Vec<(usize, u8, u8)>,
+}
+
+impl LetterBuffer {
+    fn new(text: &str) -> Self {
+        let scratch = text.as_bytes().to_vec();
+        let letters = scratch
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.is_ascii_alphabetic())
+            .map(|(i, &b)| {
+                let base = if b.is_ascii_lowercase() { b'a' } else { b'A' };
+                (i, b - base, base)
+            })
+            .collect();
+        LetterBuffer { scratch, letters }
+    }
+
+    // Rewrites every letter as `decrypt(letter, key letter)`, cycling the key.
+    fn apply(&mut self, key: &[u8], decrypt: impl Fn(u8, u8) -> u8) -> &[u8] {
+        for (n, &(i, value, base)) in self.letters.iter().enumerate() {
+            self.scratch[i] = decrypt(value, key[n % key.len()]) + base;
+        }
+        &self.scratch
+    }
+}
+
+// Key number `index` among keys of `len` letters, last letter fastest.
+fn key_at_index(mut index: u64, len: usize) -> Vec<u8> {
+    let mut key = vec![0u8; len];
+    for k in key.iter_mut().rev() {
+        *k = (index % 26) as u8;
+        index /= 26;
+    }
+    key
+}
+
+// Length of the shortest block the key repeats ("abab" -> 2).
+fn key_period(key: &[u8]) -> usize {
+    (1..key.len())
+        .filter(|p| key.len().is_multiple_of(*p))
+        .find(|&p| key.iter().enumerate().all(|(i, &k)| k == key[i % p]))
+        .unwrap_or(key.len())
+}
+
+// Tries every key with a length in `lengths` for a periodic shift cipher whose
+// per-letter decryption is `decrypt(cipher letter, key letter)`, both 0-25.
+// The key is stepped like an odometer and strings are only built for
+// candidates that make it into `top_n`. Keys that just repeat a shorter key
+// in range ("abab" after "ab") are skipped. If the session keeps checkpoints,
+// the position and `top_n` are saved every so often and when stopped;
+// `cipher` is the menu number used to match the checkpoint up again.
+fn sweep_periodic_keys(
+    session: &Session,
+    cipher: usize,
+    ciphertext: &str,
+    lengths: std::ops::RangeInclusive<usize>,
+    cipher_type: &str,
+    decrypt: impl Fn(u8, u8) -> u8,
+    top_n: &mut TopN,
+) {
+    let mut buffer = LetterBuffer::new(ciphertext);
+    let keys_below = |len: usize| (*lengths.start()..len).map(|l| 26u64.pow(l as u32)).sum::<u64>();
+    let max_len = *lengths.end();
+    let mut progress = Progress::new(keys_below(max_len + 1));
+
+    let (mut start_len, mut start_index) = (*lengths.start(), 0);
+    // Only a checkpoint this sweep wrote or resumed is removed at the end.
+    let mut owns_checkpoint = false;
+    if let Some(checkpoint) = session.take_checkpoint(cipher, ciphertext).filter(|c| lengths.contains(&c.key_len)) {
+        for result in checkpoint.results {
+            top_n.insert(result);
+        }
+        (start_len, start_index) = (checkpoint.key_len, checkpoint.index);
+        progress.tried = keys_below(start_len) + start_index;
+        owns_checkpoint = true;
+        session.status(format!(
+            "  ♻️  Resuming at {}-character key '{}'",
+            start_len,
+            key_label(&key_at_index(start_index, start_len))
+        ));
+    }
+
+    let mut last_save = std::time::Instant::now();
+    'sweep: for len in start_len..=max_len {
+        let end = 26u64.pow(len as u32);
+        let mut index = if len == start_len { start_index } else { 0 };
+        let mut key = key_at_index(index, len);
+        session.status(format!("  Trying {}-character keys...", len));
+
+        while index < end {
+            // Only the stop flag leaves a checkpoint behind. A spent budget
+            // just ends the sweep, so the next budgeted cipher can't be
+            // handed a stale checkpoint on `--resume`. The budget is checked
+            // less often; it needs the clock.
+            if session.interrupted() {
+                progress.finish(session);
+                if session.save_checkpoint(cipher, ciphertext, len, index, top_n)
+                    && let Some(state) = &session.checkpoints
+                {
+                    session.status(format!("  💾 Saved checkpoint to {}; continue with --resume.", state.path.display()));
+                }
+                return;
+            }
+            if index.is_multiple_of(PROGRESS_CHECK_EVERY) && session.out_of_time() {
+                break 'sweep;
+            }
+
+            let period = key_period(&key);
+            if period == len || period < *lengths.start() {
+                let plain = buffer.apply(&key, &decrypt);
+                let score = session.scorer.score_bytes(plain);
+                if top_n.accepts(score) {
+                    let plain = std::str::from_utf8(plain).unwrap();
+                    top_n.insert_lightweight(score, cipher_type.to_string(), format!("key: {}", key_label(&key)), plain);
+                }
+            }
+            progress.tick(session, top_n);
+            index += 1;
+
+            if index.is_multiple_of(PROGRESS_CHECK_EVERY) && last_save.elapsed() >= CHECKPOINT_INTERVAL {
+                owns_checkpoint |= session.save_checkpoint(cipher, ciphertext, len, index, top_n);
+                last_save = std::time::Instant::now();
+            }
+
+            // Advance the key, last letter fastest.
+            if let Some(pos) = key.iter().rposition(|&k| k < 25) {
+                key[pos] += 1;
+                key[pos + 1..].fill(0);
+            }
+        }
+    }
+    progress.finish(session);
+    if owns_checkpoint {
+        session.remove_checkpoint();
+    }
+}
+
+// ========== CIPHER PIPELINES ==========
+
+const PIPELINE_BEAM_WIDTH: usize = 40;
+const PIPELINE_MAX_DEPTH: usize = 3;
+const PIPELINE_MAX_KEY_LEN: usize = 3;
+// English sits around 0.066 and random text around 0.038; IoC is only
+// trusted once there are enough letters for it to settle.
+const MIN_PIPELINE_IOC: f64 = 0.055;
+const MIN_IOC_LETTERS: usize = 40;
+
+/// A cipher a pipeline stage can apply.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum StageKind {
+    Reverse,
+    Atbash,
+    Caesar,
+    RailFence,
+    Columnar,
+    Affine,
+    Vigenere,
+    Beaufort,
+}
+
+// Stage kinds tried at every depth by the automatic search. The keyed
+// polyalphabetic stages are only searched when named in a pipeline spec.
+const AUTO_PIPELINE_KINDS: &[StageKind] = &[
+    StageKind::Reverse,
+    StageKind::Atbash,
+    StageKind::Caesar,
+    StageKind::RailFence,
+    StageKind::Columnar,
+];
+
+/// A pipeline stage with its parameters fixed.
+#[derive(Clone, PartialEq, Eq)]
+pub enum Stage {
+    Reverse,
+    Atbash,
+    Caesar(u8),
+    RailFence(usize),
+    Columnar(usize),
+    Affine(u8, u8),
+    Vigenere(Vec<u8>),
+    Beaufort(Vec<u8>),
+}
+
+/// A stage of a pipeline spec: fixed, or a kind whose parameters are
+/// searched.
+pub enum StageTemplate {
+    Fixed(Stage),
+    Search(StageKind),
+}
+
+fn key_label(key: &[u8]) -> String {
+    key.iter().map(|&x| (b'a' + x) as char).collect()
+}
+
+fn columnar_key(cols: usize) -> String {
+    (0..cols).map(|i| (b'a' + i as u8) as char).collect()
+}
+
+impl Stage {
+    fn kind(&self) -> StageKind {
+        match self {
+            Stage::Reverse => StageKind::Reverse,
+            Stage::Atbash => StageKind::Atbash,
+            Stage::Caesar(_) => StageKind::Caesar,
+            Stage::RailFence(_) => StageKind::RailFence,
+            Stage::Columnar(_) => StageKind::Columnar,
+            Stage::Affine(_, _) => StageKind::Affine,
+            Stage::Vigenere(_) => StageKind::Vigenere,
+            Stage::Beaufort(_) => StageKind::Beaufort,
+        }
+    }
+
+    fn apply(&self, text: &str) -> CipherResult<String> {
+        match self {
+            Stage::Reverse => Ok(decrypt_reverse(text)),
+            Stage::Atbash => Ok(decrypt_atbash(text)),
+            Stage::Caesar(shift) => Ok(decrypt_caesar(text, *shift)),
+            Stage::RailFence(rails) => Ok(decrypt_rail_fence(text, *rails)),
+            Stage::Columnar(cols) => decrypt_columnar_transposition(text, &columnar_key(*cols)),
+            Stage::Affine(a, b) => decrypt_affine(text, *a, *b),
+            Stage::Vigenere(key) => decrypt_vigenere(text, key),
+            Stage::Beaufort(key) => decrypt_beaufort(text, key),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Stage::Reverse => "reverse".to_string(),
+            Stage::Atbash => "atbash".to_string(),
+            Stage::Caesar(shift) => format!("caesar({})", shift),
+            Stage::RailFence(rails) => format!("railfence({})", rails),
+            Stage::Columnar(cols) => format!("columnar({})", cols),
+            Stage::Affine(a, b) => format!("affine({},{})", a, b),
+            Stage::Vigenere(key) => format!("vigenere({})", key_label(key)),
+            Stage::Beaufort(key) => format!("beaufort({})", key_label(key)),
+        }
+    }
+}
+
+impl StageKind {
+    // Polyalphabetic stages are the only ones that change the IoC.
+    fn preserves_ioc(&self) -> bool {
+        !matches!(self, StageKind::Vigenere | StageKind::Beaufort)
+    }
+
+    fn candidates(&self) -> Vec<Stage> {
+        match self {
+            StageKind::Reverse => vec![Stage::Reverse],
+            StageKind::Atbash => vec![Stage::Atbash],
+            StageKind::Caesar => (1..26).map(Stage::Caesar).collect(),
+            StageKind::RailFence => (2..=15).map(Stage::RailFence).collect(),
+            StageKind::Columnar => (2..=10).map(Stage::Columnar).collect(),
+            StageKind::Affine => {
+                let coprime_a = [1, 3, 5, 7, 9, 11, 15, 17, 19, 21, 23, 25];
+                coprime_a
+                    .iter()
+                    .flat_map(|&a| (0..26).map(move |b| Stage::Affine(a, b)))
+                    .collect()
+            }
+            StageKind::Vigenere | StageKind::Beaufort => {
+                let mut stages = Vec::new();
+                for len in 1..=PIPELINE_MAX_KEY_LEN {
+                    let total = 26_usize.pow(len as u32);
+                    for i in 0..total {
+                        let mut key = vec![0u8; len];
+                        let mut n = i;
+                        for j in (0..len).rev() {
+                            key[j] = (n % 26) as u8;
+                            n /= 26;
+                        }
+                        stages.push(if *self == StageKind::Vigenere {
+                            Stage::Vigenere(key)
+                        } else {
+                            Stage::Beaufort(key)
+                        });
+                    }
+                }
+                stages
+            }
+        }
+    }
+}
+
+fn parse_key_letters(arg: &str) -> Option<Vec<u8>> {
+    if arg.is_empty() || !arg.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(arg.bytes().map(|b| b.to_ascii_lowercase() - b'a').collect())
+}
+
+/// Parses specs such as `reverse | caesar(?) | railfence(3)`. A stage with
+/// `?` (or no argument, for keyed stages) is searched.
+pub fn parse_pipeline(spec: &str) -> std::result::Result<Vec<StageTemplate>, String> {
+    let mut templates = Vec::new();
+
+    for part in spec.split('|').map(str::trim).filter(|p| !p.is_empty()) {
+        let (name, arg) = match part.split_once('(') {
+            Some((name, rest)) => {
+                let arg = rest
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("missing ')' in stage '{}'", part))?;
+                (name.trim().to_lowercase(), arg.trim())
+            }
+            None => (part.to_lowercase(), "?"),
+        };
+        let search = arg == "?" || arg.is_empty();
+        let bad_arg = || format!("invalid argument '{}' for stage '{}'", arg, name);
+
+        let template = match name.as_str() {
+            "reverse" => StageTemplate::Fixed(Stage::Reverse),
+            "atbash" => StageTemplate::Fixed(Stage::Atbash),
+            "rot13" => StageTemplate::Fixed(Stage::Caesar(13)),
+            "caesar" if search => StageTemplate::Search(StageKind::Caesar),
+            "caesar" => {
+                let shift: u8 = arg.parse().map_err(|_| bad_arg())?;
+                StageTemplate::Fixed(Stage::Caesar(shift % 26))
+            }
+            "railfence" | "rail" if search => StageTemplate::Search(StageKind::RailFence),
+            "railfence" | "rail" => StageTemplate::Fixed(Stage::RailFence(arg.parse().map_err(|_| bad_arg())?)),
+            "columnar" if search => StageTemplate::Search(StageKind::Columnar),
+            "columnar" => {
+                let cols: usize = arg.parse().map_err(|_| bad_arg())?;
+                if cols == 0 {
+                    return Err(bad_arg());
+                }
+                StageTemplate::Fixed(Stage::Columnar(cols))
+            }
+            "affine" if search => StageTemplate::Search(StageKind::Affine),
+            "affine" => {
+                let (a, b) = arg.split_once(',').ok_or_else(bad_arg)?;
+                let a: u8 = a.trim().parse().map_err(|_| bad_arg())?;
+                let b: u8 = b.trim().parse().map_err(|_| bad_arg())?;
+                if mod_inverse(a as u32, 26).is_none() {
+                    return Err(bad_arg());
+                }
+                StageTemplate::Fixed(Stage::Affine(a % 26, b % 26))
+            }
+            "vigenere" if search => StageTemplate::Search(StageKind::Vigenere),
+            "vigenere" => StageTemplate::Fixed(Stage::Vigenere(parse_key_letters(arg).ok_or_else(bad_arg)?)),
+            "beaufort" if search => StageTemplate::Search(StageKind::Beaufort),
+            "beaufort" => StageTemplate::Fixed(Stage::Beaufort(parse_key_letters(arg).ok_or_else(bad_arg)?)),
+            _ => return Err(format!("unknown stage '{}'", name)),
+        };
+        templates.push(template);
+    }
+
+    if templates.is_empty() {
+        return Err("empty pipeline".to_string());
+    }
+    Ok(templates)
+}
+
+struct BeamEntry {
+    chain: Vec<Stage>,
+    text: String,
+    score: i32,
+}
+
+fn chain_label(chain: &[Stage]) -> String {
+    chain.iter().map(Stage::label).collect::<Vec<_>>().join(" | ")
+}
+
+// Beam search over `layers`, where each layer lists the stage options at
+// that depth. Every surviving intermediate chain is reported when
+// `report_partial` is set, so the automatic search also ranks shorter
+// chains.
+fn beam_search_pipeline(session: &Session, ciphertext: &str, layers: &[Vec<Stage>], report_partial: bool, top_n: &mut TopN) {
+    let letters = ciphertext.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    let mut seen = std::collections::HashSet::new();
+    let mut beam = vec![BeamEntry { chain: Vec::new(), text: ciphertext.to_string(), score: 0 }];
+
+    for (depth, layer) in layers.iter().enumerate() {
+        if session.should_stop() {
+            break;
+        }
+        let ioc_locked = layers[depth + 1..]
+            .iter()
+            .all(|l| l.iter().all(|stage| stage.kind().preserves_ioc()));
+        let last = depth + 1 == layers.len();
+        let mut next = Vec::new();
+
+        for entry in &beam {
+            for stage in layer {
+                if entry.chain.last().map(Stage::kind) == Some(stage.kind()) && layer.len() > 1 {
+                    continue;
+                }
+
+                let Ok(text) = stage.apply(&entry.text) else {
+                    continue;
+                };
+                if !seen.insert(text.clone()) {
+                    continue;
+                }
+                if ioc_locked && letters >= MIN_IOC_LETTERS && index_of_coincidence(&text) < MIN_PIPELINE_IOC {
+                    continue;
+                }
+
+                let mut chain = entry.chain.clone();
+                chain.push(stage.clone());
+                let score = session.scorer.score(&text);
+
+                if last || report_partial {
+                    top_n.insert_lightweight(score, "Pipeline".to_string(), chain_label(&chain), &text);
+                }
+                next.push(BeamEntry { chain, text, score });
+            }
+        }
+
+        next.sort_by_key(|entry| std::cmp::Reverse(entry.score));
+        next.truncate(PIPELINE_BEAM_WIDTH);
+        beam = next;
+    }
+}
+
+/// Beam search over the stages of a parsed pipeline, innermost stage last.
+pub fn crack_pipeline(session: &Session, ciphertext: &str, templates: &[StageTemplate], top_n: &mut TopN) {
+    let layers: Vec<Vec<Stage>> = templates
+        .iter()
+        .map(|t| match t {
+            StageTemplate::Fixed(stage) => vec![stage.clone()],
+            StageTemplate::Search(kind) => kind.candidates(),
+        })
+        .collect();
+    beam_search_pipeline(session, ciphertext, &layers, false, top_n);
+}
+
+/// Beam search over chains of up to three unkeyed stages.
+pub fn crack_auto_pipeline(session: &Session, ciphertext: &str, top_n: &mut TopN) {
+    let layer: Vec<Stage> = AUTO_PIPELINE_KINDS.iter().flat_map(|k| k.candidates()).collect();
+    let layers = vec![layer; PIPELINE_MAX_DEPTH];
+    beam_search_pipeline(session, ciphertext, &layers, true, top_n);
+}
+
+#[cfg(test)]
+mod pipeline_tests {
+    use super::*;
+
+    #[test]
+    fn parses_fixed_and_searched_stages() {
+        let templates = parse_pipeline("Reverse | caesar(?) | railfence(3) | affine(5, 8) | vigenere").unwrap();
+        assert_eq!(templates.len(), 5);
+        assert!(matches!(templates[0], StageTemplate::Fixed(Stage::Reverse)));
+        assert!(matches!(templates[1], StageTemplate::Search(StageKind::Caesar)));
+        assert!(matches!(templates[2], StageTemplate::Fixed(Stage::RailFence(3))));
+        assert!(matches!(templates[3], StageTemplate::Fixed(Stage::Affine(5, 8))));
+        assert!(matches!(templates[4], StageTemplate::Search(StageKind::Vigenere)));
+    }
+
+    #[test]
+    fn rejects_bad_stages() {
+        assert_eq!(parse_pipeline("reverse | rot47").err().unwrap(), "unknown stage 'rot47'");
+        assert!(parse_pipeline("caesar(x)").is_err());
+        assert!(parse_pipeline("affine(2,3)").is_err());
+        assert!(parse_pipeline("columnar(0)").is_err());
+        assert!(parse_pipeline("railfence(3").is_err());
+        assert!(parse_pipeline(" | ").is_err());
+    }
+
+    #[test]
+    fn recovers_caesar_then_reverse() {
+        let plain = "Meet me at the old mill after midnight and bring the map";
+        // Caesar +3 first, then reversed: the outer stage is undone first.
+        let ciphertext = decrypt_reverse(&decrypt_caesar(plain, 23));
+
+        let (scorer, config) = (Scorer::default(), SearchConfig::default());
+        let mut top_n = TopN::new(5);
+        let templates = parse_pipeline("reverse | caesar(?)").unwrap();
+        crack_pipeline(&Session::new(&scorer, &config), &ciphertext, &templates, &mut top_n);
+        let best = top_n.best_result().unwrap();
+        assert_eq!(best.params, "reverse | caesar(3)");
+        assert_eq!(best.plaintext_full, plain);
+    }
+}
+
+/// Runs the attack for one menu number (see [`CIPHER_NAMES`]).
+pub fn crack_specific_cipher(session: &Session, choice: usize, ciphertext: &str, top_n: &mut TopN) {
+    let scorer = session.scorer;
+    let config = session.config;
+    session.status("\n🔍 Attempting to crack with chosen cipher...");
+
+    match choice {
+        1 => {
+            session.status("Testing Caesar cipher (all 26 shifts)...");
+            for shift in 0..26 {
+                let plain = decrypt_caesar(ciphertext, shift);
+                let score = scorer.score(&plain);
+                top_n.insert_lightweight(score, "Caesar".to_string(), format!("shift {}", shift), &plain);
+            }
+        }
+        2 => {
+            session.status("Testing ROT13...");
+            let plain = decrypt_rot13(ciphertext);
+            let score = scorer.score(&plain);
+            top_n.insert_lightweight(score, "ROT13".to_string(), "ROT13".to_string(), &plain);
+        }
+        3 => {
+            session.status("Testing Atbash cipher...");
+            let plain = decrypt_atbash(ciphertext);
+            let score = scorer.score(&plain);
+            top_n.insert_lightweight(score, "Atbash".to_string(), "Atbash".to_string(), &plain);
+        }
+        4 => {
+            let lengths = config.vigenere_keys.clone();
+            session.status(format!("Testing Vigenère cipher ({} char keys)...", describe_range(&lengths)));
+            sweep_periodic_keys(session, 4, ciphertext, lengths, "Vigenère", |c, k| (c + 26 - k) % 26, top_n);
+        }
+        5 => {
+            let rails_range = config.rails.clone();
+            session.status(format!("Testing Rail Fence cipher ({} rails)...", describe_range(&rails_range)));
+            for rails in rails_range {
+                let plain = decrypt_rail_fence(ciphertext, rails);
+                let score = scorer.score(&plain);
+                top_n.insert_lightweight(score, "Rail Fence".to_string(), format!("{} rails", rails), &plain);
+            }
+        }
+        6 => {
+            session.status("Testing Affine cipher (all combinations)...");
+            let coprime_a = vec![1, 3, 5, 7, 9, 11, 15, 17, 19, 21, 23, 25];
+            for &a in &coprime_a {
+                for b in 0..26 {
+                    if let Ok(plain) = decrypt_affine(ciphertext, a, b) {
+                        let score = scorer.score(&plain);
+                        top_n.insert_lightweight(score, "Affine".to_string(), format!("a={}, b={}", a, b), &plain);
+                    }
+                }
+            }
+        }
+        7 => {
+            let lengths = config.beaufort_keys.clone();
+            session.status(format!("Testing Beaufort cipher ({} char keys)...", describe_range(&lengths)));
+            sweep_periodic_keys(session, 7, ciphertext, lengths, "Beaufort", |c, k| (k + 26 - c) % 26, top_n);
+        }
+        8 => {
+            let columns = config.columns.clone();
+            session.status(format!("Testing Columnar Transposition ({} cols)...", describe_range(&columns)));
+            for cols in columns {
+                let mut key = String::new();
+                for i in 0..cols {
+                    key.push((b'a' + (i as u8)) as char);
+                }
+                if let Ok(plain) = decrypt_columnar_transposition(ciphertext, &key) {
+                    let score = scorer.score(&plain);
+                    top_n.insert_lightweight(score, "Columnar".to_string(), format!("{} cols", cols), &plain);
+                }
+            }
+        }
+        9 => {
+            session.status("Testing Playfair cipher (common keys)...");
+            for key in &config.playfair_keys {
+                match decrypt_playfair(ciphertext, key) {
+                    Ok(plain) => {
+                        let plain = scorer.segment_words(&plain);
+                        let score = scorer.score(&plain);
+                        top_n.insert_lightweight(score, "Playfair".to_string(), format!("key: {}", key), &plain);
+                    }
+                    Err(e) => {
+                        session.status(format!("  Skipping Playfair: {}", e));
+                        break;
+                    }
+                }
+            }
+        }
+        10 => {
+            session.status("Testing Polybius Square...");
+            match decrypt_polybius_square(ciphertext) {
+                Ok(plain) => {
+                    let plain = scorer.segment_words(&plain);
+                    let score = scorer.score(&plain);
+                    top_n.insert_lightweight(score, "Polybius".to_string(), "Polybius Square".to_string(), &plain);
+                }
+                Err(e) => session.status(format!("  Skipping Polybius: {}", e)),
+            }
+        }
+        11 => {
+            session.status("Testing Bacon cipher (detected variants)...");
+            for variant in detect_bacon_variants(ciphertext) {
+                let plain = scorer.segment_words(&decrypt_bacon(ciphertext, variant));
+                let score = scorer.score(&plain);
+                top_n.insert_lightweight(score, "Bacon".to_string(), variant.describe(), &plain);
+            }
+        }
+        12 => {
+            session.status("Testing Reverse cipher...");
+            let plain = decrypt_reverse(ciphertext);
+            let score = scorer.score(&plain);
+            top_n.insert_lightweight(score, "Reverse".to_string(), "Reverse".to_string(), &plain);
+        }
+        13 => {
+            let lengths = config.hybrid_keys.clone();
+            session.status(format!("Testing Atbash + Vigenère Hybrid ({} char keys)...", describe_range(&lengths)));
+            sweep_periodic_keys(session, 13, ciphertext, lengths, "Hybrid", atbash_vigenere_letter, top_n);
+        }
+        14 => {
+            session.status("Testing XOR (single-byte + repeating-key)...");
+            let bytes = ciphertext_bytes(ciphertext);
+            crack_single_byte_xor(session, &bytes, top_n);
+            crack_repeating_xor(session, &bytes, top_n);
+        }
+        15 => {
+            session.status(format!("Testing cipher pipelines (beam search, up to {} stages)...", PIPELINE_MAX_DEPTH));
+            crack_auto_pipeline(session, ciphertext, top_n);
+        }
+        16 => {
+            session.status("Testing Running Key cipher (plaintext/key split)...");
+            crack_running_key_statistical(session, ciphertext, top_n);
+        }
+        17 => {
+            session.status("Testing Enigma I (rotor orders, positions, plugboard)...");
+            crack_enigma(session, ciphertext, EnigmaModel::I, top_n);
+        }
+        18 => {
+            session.status(format!("Testing Homophonic substitution (simulated annealing, {} restarts)...", HOMOPHONIC_RESTARTS));
+            crack_homophonic(session, ciphertext, config.seed(), top_n);
+        }
+        19 => {
+            session.status("Testing Straddling Checkerboard (blank positions + layout)...");
+            crack_checkerboard(session, ciphertext, false, top_n);
+        }
+        20 => {
+            session.status("Testing Chaocipher and Solitaire (common-word passphrases)...");
+            crack_passphrase_stream_ciphers(session, ciphertext, top_n);
+        }
+        21 => {
+            session.status("Testing Keyword substitution (common-word keywords, 26 shifts)...");
+            crack_keyword_substitution(session, ciphertext, COMMON_WORDS, top_n);
+        }
+        _ => {}
+    }
+}
+
+/// Runs one cipher, stopping its search once `budget` has passed.
+pub fn run_cipher(session: &Session, cipher: usize, ciphertext: &str, top_n: &mut TopN, budget: Option<std::time::Duration>) {
+    session.deadline.set(budget.map(|b| std::time::Instant::now() + b));
+    crack_specific_cipher(session, cipher, ciphertext, top_n);
+    if let Some(budget) = budget.filter(|_| session.out_of_time() && !session.interrupted()) {
+        session.status(format!("  ⏱️  Time budget of {} used up for {}.", format_duration(budget.as_secs_f64()), CIPHER_NAMES[cipher - 1]));
+    }
+    session.deadline.set(None);
+}
+
+// Rough cost of each search by menu number: 1 is a handful of decryptions,
+// 3 a key-space sweep or hill climb.
+fn cipher_cost(cipher: usize) -> u8 {
+    match cipher {
+        4 | 7 | 13 | 15 | 16 | 17 | 18 => 3,
+        14 | 19 | 20 | 21 => 2,
+        _ => 1,
+    }
+}
+
+// How well the ciphertext fits a cipher family: 0 unlikely, 1 no idea,
+// 2 likely. Letter statistics only count once there are enough letters.
+fn cipher_promise(cipher: usize, ciphertext: &str) -> u8 {
+    let letters = ciphertext.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    let symbols: Vec<char> = ciphertext.chars().filter(|c| !c.is_whitespace()).collect();
+    let digits = !symbols.is_empty() && symbols.iter().all(|c| c.is_ascii_digit());
+    let ioc = index_of_coincidence(ciphertext);
+    let monoalphabetic = letters >= MIN_IOC_LETTERS && ioc >= MIN_PIPELINE_IOC;
+    let polyalphabetic = letters >= MIN_IOC_LETTERS && ioc < MIN_PIPELINE_IOC;
+
+    match cipher {
+        10 | 19 => if digits { 2 } else { 0 },
+        11 => if detect_bacon_variants(ciphertext).is_empty() { 0 } else { 2 },
+        _ if digits => 0,
+        1 | 2 | 3 | 5 | 6 | 8 | 12 | 21 => match (monoalphabetic, polyalphabetic) {
+            (true, _) => 2,
+            (_, true) => 0,
+            _ => 1,
+        },
+        4 | 7 | 13 | 16 | 17 | 20 => match (monoalphabetic, polyalphabetic) {
+            (_, true) => 2,
+            (true, _) => 0,
+            _ => 1,
+        },
+        9 => if letters.is_multiple_of(2) && !ciphertext.contains(['j', 'J']) { 1 } else { 0 },
+        _ => 1,
+    }
+}
+
+/// The order an all-ciphers run tries things in.
+pub fn schedule_ciphers(config: &SearchConfig, ciphertext: &str) -> Vec<usize> {
+    let mut order: Vec<usize> = (1..=CIPHER_COUNT).collect();
+    if config.scheduler == Scheduler::Promising {
+        order.sort_by_key(|&c| (std::cmp::Reverse(cipher_promise(c, ciphertext)), cipher_cost(c)));
+    }
+    order
+}
+
+/// Runs every cipher in scheduled order, sharing the configured time
+/// budget.
+pub fn crack_all_ciphers(session: &Session, ciphertext: &str, top_n: &mut TopN) {
+    let config = session.config;
+    session.status("\n🔍 Brute forcing ALL ciphers...");
+
+    let mut order = schedule_ciphers(config, ciphertext);
+    if let Some(pos) = session.resume_first_cipher().and_then(|first| order.iter().position(|&c| c == first)) {
+        order.drain(..pos);
+    }
+    let names: Vec<&str> = order.iter().map(|&c| CIPHER_NAMES[c - 1]).collect();
+    session.status(format!("  Order: {}", names.join(", ")));
+
+    // What is left of the run budget is shared evenly by the ciphers still
+    // to go, so time a cheap search doesn't use passes on to the later ones.
+    let started = std::time::Instant::now();
+    for (done, &cipher) in order.iter().enumerate() {
+        if session.interrupted() {
+            break;
+        }
+        let mut budget = config.cipher_budgets.get(&cipher).copied();
+        if let Some(total) = config.budget {
+            let left = total.saturating_sub(started.elapsed());
+            if left.is_zero() {
+                session.status(format!("  ⏱️  Run budget used up; skipping {}.", names[done..].join(", ")));
+                break;
+            }
+            let share = left / (order.len() - done) as u32;
+            budget = Some(budget.map_or(share, |b| b.min(share)));
+        }
+        run_cipher(session, cipher, ciphertext, top_n, budget);
+    }
+}
+
+/// Runs the attack on the ciphertext itself and again on every encoding layer
+/// peeled off it. Results from decoded layers carry the layer chain in params.
+pub fn crack_with_layers<F>(session: &Session, ciphertext: &str, top_n: &mut TopN, mut crack: F)
+where
+    F: FnMut(&str, &mut TopN),
+{
+    crack(ciphertext, top_n);
+
+    for input in peel_encodings(ciphertext) {
+        if session.interrupted() {
+            break;
+        }
+        let chain = input.layers.join(" → ");
+        session.status(format!("\n🧅 Decoded layer(s) [{}]: \"{}\"", chain, input.text));
+
+        let mut layer_top = TopN::new(top_n.limit);
+        let score = session.scorer.score(&input.text);
+        layer_top.insert_lightweight(score, "Encoding".to_string(), chain.clone(), &input.text);
+        crack(&input.text, &mut layer_top);
+
+        for mut result in layer_top.into_sorted_vec() {
+            if result.cipher_type != "Encoding" {
+                result.params = format!("[{}] {}", chain, result.params);
+            }
+            top_n.insert(result);
+        }
+    }
+}
+
+// ========== BATCH ==========
+
+/// One ciphertext of a batch run, named after the file (and line) it came from.
+pub struct BatchInput {
+    pub name: String,
+    pub ciphertext: String,
+}
+
+/// What a batch run found for one input.
+pub struct BatchOutcome {
+    pub name: String,
+    pub ciphertext: String,
+    /// The best decryption, if any cipher produced one.
+    pub best: Option<CrackResult>,
+    /// Confidence in `best`.
+    pub confidence: Option<Confidence>,
+    /// Set when the confidence is below the success threshold.
+    pub flagged: bool,
+    pub elapsed: std::time::Duration,
+}
+
+/// Cracks each input on its own with the menu's all-ciphers search, on up to
+/// `jobs` worker threads. The workers share the session's scorer, settings
+/// and stop flag, but report nothing and write no checkpoints. `on_done`
+/// sees every outcome as it finishes; the returned outcomes are in input
+/// order. Once the stop flag is set, inputs that hadn't started yet are left
+/// out.
+pub fn crack_batch<F>(session: &Session, inputs: &[BatchInput], jobs: usize, on_done: F) -> Vec<BatchOutcome>
+where
+    F: Fn(&BatchOutcome) + Sync,
+{
+    let (scorer, config, stop) = (session.scorer, session.config, session.stop);
+    let next = std::sync::atomic::AtomicUsize::new(0);
+    let outcomes: std::sync::Mutex<Vec<Option<BatchOutcome>>> =
+        std::sync::Mutex::new(inputs.iter().map(|_| None).collect());
+
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, inputs.len().max(1)) {
+            scope.spawn(|| {
+                let mut worker = Session::new(scorer, config);
+                worker.stop = stop;
+                loop {
+                    let i = next.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    if i >= inputs.len() || worker.interrupted() {
+                        break;
+                    }
+                    let outcome = crack_batch_input(&worker, &inputs[i]);
+                    on_done(&outcome);
+                    outcomes.lock().unwrap()[i] = Some(outcome);
+                }
+            });
+        }
+    });
+    outcomes.into_inner().unwrap().into_iter().flatten().collect()
+}
+
+fn crack_batch_input(session: &Session, input: &BatchInput) -> BatchOutcome {
+    let started = std::time::Instant::now();
+    let mut top_n = TopN::new(5);
+    crack_with_layers(session, &input.ciphertext, &mut top_n, |text, top| crack_all_ciphers(session, text, top));
+
+    // Confidence is judged against the runners-up, as on the menu.
+    let results = top_n.into_sorted_vec();
+    let confidence = assess_results(session.scorer, &results).into_iter().next();
+    let flagged = confidence.as_ref().is_none_or(|c| c.percent < session.config.success_confidence);
+    BatchOutcome {
+        name: input.name.clone(),
+        ciphertext: input.ciphertext.clone(),
+        best: results.into_iter().next(),
+        confidence,
+        flagged,
+        elapsed: started.elapsed(),
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The batch report as JSON: the success threshold, then per input its best
+/// cipher, key, plaintext and confidence. Inputs with no result have `null`
+/// for those.
+pub fn batch_report_json(outcomes: &[BatchOutcome], success_confidence: u8) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+    let entries: Vec<String> = outcomes
+        .iter()
+        .map(|o| {
+            let best = o.best.as_ref();
+            let confidence = o.confidence.as_ref();
+            let fields = [
+                ("name", json_string(&o.name)),
+                ("ciphertext", json_string(&o.ciphertext)),
+                ("cipher", optional(best.map(|b| json_string(&b.cipher_type)))),
+                ("key", optional(best.map(|b| json_string(&b.params)))),
+                ("plaintext", optional(best.map(|b| json_string(&b.plaintext_full)))),
+                ("score", optional(best.map(|b| b.score.to_string()))),
+                ("confidence", optional(confidence.map(|c| c.percent.to_string()))),
+                ("language", optional(confidence.map(|c| json_string(c.language.code())))),
+                ("flagged", o.flagged.to_string()),
+                ("seconds", format!("{:.1}", o.elapsed.as_secs_f64())),
+            ];
+            let fields: Vec<String> = fields.iter().map(|(k, v)| format!("      \"{}\": {}", k, v)).collect();
+            format!("    {{\n{}\n    }}", fields.join(",\n"))
+        })
+        .collect();
+    format!(
+        "{{\n  \"success_confidence\": {},\n  \"inputs\": [\n{}\n  ]\n}}\n",
+        success_confidence,
+        entries.join(",\n")
+    )
+}
+
+#[cfg(test)]
+mod batch_tests {
+    use super::*;
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("plain"), r#""plain""#);
+        assert_eq!(json_string("say \"hi\"\\"), r#""say \"hi\"\\""#);
+        assert_eq!(json_string("a\nb\rc\td"), r#""a\nb\rc\td""#);
+        assert_eq!(json_string("\0\u{1b}\u{7f}\u{85}"), r#""\u0000\u001b\u007f\u0085""#);
+        assert_eq!(json_string("café 中文 🙂"), "\"café 中文 🙂\"");
+    }
+
+    #[test]
+    fn report_escapes_fields_and_writes_null_for_no_result() {
+        let solved = BatchOutcome {
+            name: "in\\box \"1\".txt".to_string(),
+            ciphertext: "Uryyb\tjbeyq\n".to_string(),
+            best: Some(CrackResult {
+                score: 321,
+                cipher_type: "ROT13".to_string(),
+                params: "ROT13".to_string(),
+                plaintext_preview: "Hello\tworld\n".to_string(),
+                plaintext_full: "Hello\tworld\n".to_string(),
+                equivalents: Vec::new(),
+            }),
+            confidence: Some(Confidence { percent: 87, language: Language::English, z_score: 4.0, coverage: 1.0, margin: 0.5 }),
+            flagged: false,
+            elapsed: std::time::Duration::from_millis(1500),
+        };
+        let unsolved = BatchOutcome {
+            name: "empty".to_string(),
+            ciphertext: String::new(),
+            best: None,
+            confidence: None,
+            flagged: true,
+            elapsed: std::time::Duration::ZERO,
+        };
+
+        let report = batch_report_json(&[solved, unsolved], 60);
+        assert!(report.starts_with("{\n  \"success_confidence\": 60,\n  \"inputs\": [\n"));
+        for line in [
+            r#""name": "in\\box \"1\".txt","#,
+            r#""ciphertext": "Uryyb\tjbeyq\n","#,
+            r#""plaintext": "Hello\tworld\n","#,
+            r#""confidence": 87,"#,
+            r#""language": "en","#,
+            r#""seconds": 1.5"#,
+            r#""key": null,"#,
+            r#""confidence": null,"#,
+            r#""flagged": true,"#,
+        ] {
+            assert!(report.contains(line), "missing {} in\n{}", line, report);
+        }
+        assert!(report.contains("    },\n    {\n") && report.ends_with("    }\n  ]\n}\n"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREEK: &str = "αβγδεζηθικλμνξοπρστυφχψω";
+
+    // Accented and non-Latin letters, combining marks, emoji and controls, so
+    // byte-based code paths meet multi-byte chars.
+    const AWKWARD: &[char] = &[
+        'é', 'É', 'ß', 'ø', 'Æ', 'ı', 'İ', 'ſ', 'K', 'α', 'λ', 'ω', 'Ω', 'я', '中', 'ひ', '\u{301}', '\u{200b}', '🙂',
+        '\0', '\t', '\n', '\r', '\x0c', '\x7f', ' ', '.', ',', ':', '-', '/', '0', '5', '9',
+    ];
+
+    // ASCII letters, AWKWARD and random code points, from a fixed seed so a
+    // failure replays.
+    fn arbitrary_texts() -> Vec<String> {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        (0..200)
+            .map(|_| {
+                let len = rng.below(61);
+                (0..len)
+                    .map(|_| match rng.below(4) {
+                        0 => (b'a' + rng.below(26) as u8) as char,
+                        1 => (b'A' + rng.below(26) as u8) as char,
+                        2 => AWKWARD[rng.below(AWKWARD.len())],
+                        _ => char::from_u32(rng.below(0x11_0000) as u32).unwrap_or('\u{fffd}'),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    type Transform = Box<dyn Fn(&str) -> CipherResult<String>>;
+
+    // One cipher under a fixed key. `plaintext` narrows any text to what the
+    // cipher can carry, and `encrypt` must be undone by `decrypt` on it.
+    struct Case {
+        name: &'static str,
+        plaintext: fn(&str) -> String,
+        encrypt: Transform,
+        decrypt: Transform,
+    }
+
+    fn case(name: &'static str, encrypt: Transform, decrypt: Transform) -> Case {
+        Case { name, plaintext: str::to_string, encrypt, decrypt }
+    }
+
+    fn infallible(f: impl Fn(&str) -> String + 'static) -> Transform {
+        Box::new(move |text| Ok(f(text)))
+    }
+
+    fn ascii_letters(text: &str) -> String {
+        text.chars().filter(char::is_ascii_alphabetic).map(|c| c.to_ascii_lowercase()).collect()
+    }
+
+    // Encryption for a per-letter substitution, read off from how it
+    // decrypts a-z.
+    fn inverse_substitution(decrypt: impl Fn(&str) -> CipherResult<String>) -> Transform {
+        let mut table = [0u8; 26];
+        for (i, c) in decrypt("abcdefghijklmnopqrstuvwxyz").unwrap().bytes().enumerate() {
+            table[(c - b'a') as usize] = i as u8;
+        }
+        infallible(move |text| map_letters(text, |p| table[p as usize]))
+    }
+
+    // Encryption for a transposition: decrypting distinct symbols shows which
+    // position each output character was read from.
+    fn inverse_transposition(decrypt: fn(&str) -> String) -> Transform {
+        infallible(move |text| {
+            let chars: Vec<char> = text.chars().collect();
+            let probe: String = (0..chars.len() as u32).filter_map(|i| char::from_u32(0xF_0000 + i)).collect();
+            let mut out = vec![' '; chars.len()];
+            for (c, source) in chars.iter().zip(decrypt(&probe).chars()) {
+                out[(source as u32 - 0xF_0000) as usize] = *c;
+            }
+            out.into_iter().collect()
+        })
+    }
+
+    fn cases() -> Vec<Case> {
+        let greek = Alphabet::parse(GREEK).unwrap();
+        let n = greek.len();
+        let vigenere_key = [11, 4, 12, 14, 13];
+        let negated: Vec<u8> = vigenere_key.iter().map(|k| (26 - k) % 26).collect();
+        let greek_key = [3, 17, 0, 22];
+        let greek_negated: Vec<usize> = greek_key.iter().map(|k| (n - k) % n).collect();
+        let (affine_a, affine_b) = (1..n)
+            .flat_map(|a| (0..n).map(move |b| (a, b)))
+            .find(|&(a, b)| {
+                let encrypted = decrypt_affine_in(GREEK, a, b, &greek).unwrap_or_default();
+                decrypt_affine_in(&encrypted, 5, 7, &greek).as_deref() == Ok(GREEK)
+            })
+            .unwrap();
+        let enigma = parse_enigma_settings("B", "II,IV,V", "BUL", "XYZ", "AV BS CG DL").unwrap();
+        let board = Checkerboard::from_keyword("asinorte", [2, 6]).unwrap();
+        let chao = Chaocipher::from_passphrase("kryptos");
+        let deck = Solitaire::from_passphrase("foo");
+        let mixed = keyword_alphabet("kryptos", true);
+
+        vec![
+            case("Caesar", infallible(|t| decrypt_caesar(t, 19)), infallible(|t| decrypt_caesar(t, 7))),
+            case("ROT13", infallible(decrypt_rot13), infallible(decrypt_rot13)),
+            case("Atbash", infallible(decrypt_atbash), infallible(decrypt_atbash)),
+            case("Reverse", infallible(decrypt_reverse), infallible(decrypt_reverse)),
+            case(
+                "Vigenère",
+                Box::new(move |t| decrypt_vigenere(t, &negated)),
+                Box::new(move |t| decrypt_vigenere(t, &vigenere_key)),
+            ),
+            case(
+                "Beaufort",
+                Box::new(move |t| decrypt_beaufort(t, &vigenere_key)),
+                Box::new(move |t| decrypt_beaufort(t, &vigenere_key)),
+            ),
+            case(
+                "Atbash + Vigenère",
+                Box::new(|t| decrypt_vigenere(t, &[15, 22, 14]).map(|v| decrypt_atbash(&v))),
+                Box::new(|t| decrypt_atbash_vigenere(t, &[11, 4, 12])),
+            ),
+            case("Affine", inverse_substitution(|t| decrypt_affine(t, 5, 8)), Box::new(|t| decrypt_affine(t, 5, 8))),
+            {
+                let mixed_copy = mixed.clone();
+                case(
+                    "Keyword",
+                    inverse_substitution(move |t| decrypt_keyword(t, &mixed, 3)),
+                    Box::new(move |t| decrypt_keyword(t, &mixed_copy, 3)),
+                )
+            },
+            case("Rail fence", inverse_transposition(|t| decrypt_rail_fence(t, 3)), infallible(|t| decrypt_rail_fence(t, 3))),
+            case(
+                "Columnar",
+                Box::new(|t| encrypt_columnar_transposition(t, "zebras")),
+                Box::new(|t| decrypt_columnar_transposition(t, "zebras")),
+            ),
+            {
+                let (g1, g2) = (greek.clone(), greek.clone());
+                case(
+                    "Caesar (Greek)",
+                    infallible(move |t| decrypt_caesar_in(t, n - 9, &g1)),
+                    infallible(move |t| decrypt_caesar_in(t, 9, &g2)),
+                )
+            },
+            {
+                let (g1, g2) = (greek.clone(), greek.clone());
+                case(
+                    "Vigenère (Greek)",
+                    Box::new(move |t| decrypt_vigenere_in(t, &greek_negated, &g1)),
+                    Box::new(move |t| decrypt_vigenere_in(t, &greek_key, &g2)),
+                )
+            },
+            {
+                let (g1, g2) = (greek.clone(), greek.clone());
+                case(
+                    "Beaufort (Greek)",
+                    Box::new(move |t| decrypt_beaufort_in(t, &greek_key, &g1)),
+                    Box::new(move |t| decrypt_beaufort_in(t, &greek_key, &g2)),
+                )
+            },
+            {
+                let g1 = greek.clone();
+                case(
+                    "Affine (Greek)",
+                    Box::new(move |t| decrypt_affine_in(t, affine_a, affine_b, &g1)),
+                    Box::new(move |t| decrypt_affine_in(t, 5, 7, &greek)),
+                )
+            },
+            {
+                let copy = enigma.clone();
+                case("Enigma", infallible(move |t| decrypt_enigma(t, &enigma)), infallible(move |t| decrypt_enigma(t, &copy)))
+            },
+            {
+                let copy = chao.clone();
+                case("Chaocipher", infallible(move |t| chao.encrypt(t)), infallible(move |t| copy.decrypt(t)))
+            },
+            Case {
+                name: "Playfair",
+                // Letters the square can carry in digraphs it can encrypt:
+                // no j or x (padding) and no pair of equal letters.
+                plaintext: |t| {
+                    let mut out = String::new();
+                    for c in ascii_letters(t).chars().filter(|&c| c != 'j' && c != 'x') {
+                        if !out.len().is_multiple_of(2) && out.ends_with(c) {
+                            continue;
+                        }
+                        out.push(c);
+                    }
+                    if !out.len().is_multiple_of(2) {
+                        out.pop();
+                    }
+                    out
+                },
+                encrypt: {
+                    let letters: Vec<char> = ('a'..='z').filter(|&c| c != 'j').collect();
+                    let mut table = std::collections::HashMap::new();
+                    for &a in &letters {
+                        for &b in &letters {
+                            let digraph = format!("{}{}", a, b);
+                            table.insert(decrypt_playfair(&digraph, "playfair example").unwrap(), digraph);
+                        }
+                    }
+                    infallible(move |t| {
+                        let chars: Vec<char> = t.chars().collect();
+                        chars.chunks(2).map(|pair| table[&pair.iter().collect::<String>()].clone()).collect()
+                    })
+                },
+                decrypt: Box::new(|t| decrypt_playfair(t, "playfair example")),
+            },
+            Case {
+                name: "Polybius",
+                plaintext: |t| ascii_letters(t).replace('j', "i"),
+                encrypt: infallible(|t| {
+                    let square = "abcdefghiklmnopqrstuvwxyz";
+                    t.chars()
+                        .filter_map(|c| square.find(c))
+                        .map(|i| format!("{}{} ", i / 5 + 1, i % 5 + 1))
+                        .collect()
+                }),
+                decrypt: Box::new(decrypt_polybius_square),
+            },
+            Case {
+                name: "Bacon",
+                plaintext: ascii_letters,
+                encrypt: infallible(|t| {
+                    t.bytes()
+                        .flat_map(|c| (0..5).rev().map(move |bit| if (c - b'a') >> bit & 1 == 1 { 'b' } else { 'a' }))
+                        .collect()
+                }),
+                decrypt: infallible(|t| {
+                    let variant = BaconVariant { table: BaconTable::Full26, encoding: BaconEncoding::Letters, swapped: false };
+                    decrypt_bacon(t, variant)
+                }),
+            },
+            Case {
+                name: "Checkerboard",
+                plaintext: |t| {
+                    t.chars()
+                        .map(|c| c.to_ascii_lowercase())
+                        .filter(|&c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.')
+                        .collect()
+                },
+                encrypt: {
+                    let board = board.clone();
+                    infallible(move |t| encrypt_checkerboard(t, &board))
+                },
+                decrypt: infallible(move |t| decrypt_checkerboard(t, &board)),
+            },
+            Case {
+                name: "Solitaire",
+                // Upper-case letters padded with X to whole groups of five.
+                plaintext: |t| {
+                    let mut letters: Vec<char> = ascii_letters(t).to_uppercase().chars().collect();
+                    while !letters.len().is_multiple_of(5) {
+                        letters.push('X');
+                    }
+                    letters.chunks(5).map(|g| g.iter().collect::<String>()).collect::<Vec<_>>().join(" ")
+                },
+                encrypt: {
+                    let deck = deck.clone();
+                    infallible(move |t| deck.encrypt(t))
+                },
+                decrypt: infallible(move |t| deck.decrypt(t)),
+            },
+        ]
+    }
+
+    #[test]
+    fn every_cipher_takes_any_text_and_round_trips() {
+        let texts = arbitrary_texts();
+        for case in cases() {
+            for text in &texts {
+                // Arbitrary text may be rejected, but must not panic.
+                let _ = (case.decrypt)(text);
+                let plain = (case.plaintext)(text);
+                let encrypted = (case.encrypt)(&plain).unwrap();
+                assert_eq!((case.decrypt)(&encrypted), Ok(plain), "{} on {:?}", case.name, text);
+            }
+        }
+    }
+
+    #[test]
+    fn greek_round_trip_changes_the_greek_letters() {
+        let greek = Alphabet::parse(GREEK).unwrap();
+        let text = "λόγος καὶ ἔργον, logos";
+        let encrypted = decrypt_caesar_in(text, 24 - 9, &greek);
+        assert_ne!(encrypted, text);
+        assert!(encrypted.ends_with(", logos"));
+        assert_eq!(decrypt_caesar_in(&encrypted, 9, &greek), text);
+    }
+}